@@ -0,0 +1,38 @@
+//! Pluggable message generation backends
+//!
+//! This module defines the [`MessageGenerator`] trait that turns a fully
+//! built prompt into a commit message. The default implementation is
+//! [`ClaudeCliBackend`](crate::claude::ClaudeCliBackend); downstream crates
+//! can implement the trait for other models (OpenAI, Ollama, ...) without
+//! forking.
+
+use anyhow::Result;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Boxed future returned by [`MessageGenerator::generate`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A backend that generates a commit message from a prompt
+///
+/// The method returns a boxed future so the trait can be used as
+/// `&dyn MessageGenerator`. Implementations typically wrap an `async`
+/// block with `Box::pin`.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::backend::{BoxFuture, MessageGenerator};
+///
+/// struct FixedBackend;
+///
+/// impl MessageGenerator for FixedBackend {
+///     fn generate<'a>(&'a self, _prompt: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+///         Box::pin(async { Ok("chore: update files".to_string()) })
+///     }
+/// }
+/// ```
+pub trait MessageGenerator: Send + Sync {
+    /// Generate a commit message for the given prompt
+    fn generate<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<String>>;
+}
@@ -0,0 +1,233 @@
+//! Cache of generated messages keyed by prompt
+//!
+//! Re-running after a failed or aborted commit usually sends the exact same
+//! prompt again. The last messages are stored in
+//! `<git dir>/claude_commit_cache.json` so such runs can skip calling Claude.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+use crate::git::get_git_dir;
+
+/// File name of the cache inside the git directory
+pub const CACHE_FILE: &str = "claude_commit_cache.json";
+
+/// Default time-to-live of cached messages: 24 hours
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// A cached message and when it was generated
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// The generated commit message
+    pub message: String,
+    /// Generation time in seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+/// Messages keyed by [`prompt_key`]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MessageCache {
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl MessageCache {
+    /// Read the cache from `path`
+    ///
+    /// A missing or unreadable cache is treated as empty, so a corrupt file
+    /// never prevents generating a message.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the cache to `path`
+    ///
+    /// # Errors
+    ///
+    /// * Failed to write the file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string(self).expect("cache entries always serialize");
+        fs::write(path, content)
+            .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))
+    }
+
+    /// Message cached under `key`, unless it is older than `ttl_secs` at `now`
+    pub fn get(&self, key: &str, ttl_secs: u64, now: u64) -> Option<&str> {
+        self.entries
+            .get(key)
+            .filter(|entry| !is_expired(entry, ttl_secs, now))
+            .map(|entry| entry.message.as_str())
+    }
+
+    /// Store `message` under `key`, generated at `now`
+    pub fn insert(&mut self, key: String, message: &str, now: u64) {
+        self.entries.insert(
+            key,
+            CacheEntry {
+                message: message.to_string(),
+                created_at: now,
+            },
+        );
+    }
+
+    /// Drop every entry older than `ttl_secs` at `now`
+    pub fn prune(&mut self, ttl_secs: u64, now: u64) {
+        self.entries
+            .retain(|_, entry| !is_expired(entry, ttl_secs, now));
+    }
+}
+
+/// Whether `entry` is older than `ttl_secs` at `now`
+fn is_expired(entry: &CacheEntry, ttl_secs: u64, now: u64) -> bool {
+    now.saturating_sub(entry.created_at) > ttl_secs
+}
+
+/// Cache key of a prompt
+///
+/// The prompt already contains the template, the diff and every optional
+/// section, so any change to them produces a different key. Callers prepend
+/// the generation settings (model, system prompt, ...) to the prompt so that
+/// changing them misses the cache too. Uses 64-bit FNV-1a, which (unlike
+/// `DefaultHasher`) is stable across Rust versions.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::cache::prompt_key;
+///
+/// assert_eq!(prompt_key("a"), "af63dc4c8601ec8c");
+/// ```
+pub fn prompt_key(prompt: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = prompt.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Path of the cache file in the current repository
+///
+/// # Errors
+///
+/// * Not in a git repository
+pub fn cache_path() -> Result<PathBuf> {
+    Ok(get_git_dir()?.join(CACHE_FILE))
+}
+
+/// Look up a cached message for `prompt` in the current repository
+///
+/// Returns `None` when there is no fresh entry or the cache cannot be located.
+pub fn lookup(prompt: &str, ttl_secs: u64) -> Option<String> {
+    let path = cache_path().ok()?;
+    MessageCache::load(&path)
+        .get(&prompt_key(prompt), ttl_secs, now())
+        .map(str::to_string)
+}
+
+/// Cache `message` for `prompt` in the current repository, dropping expired entries
+///
+/// # Errors
+///
+/// * Not in a git repository
+/// * Failed to write the cache file
+pub fn store(prompt: &str, message: &str, ttl_secs: u64) -> Result<()> {
+    let path = cache_path()?;
+    let now = now();
+    let mut cache = MessageCache::load(&path);
+    cache.prune(ttl_secs, now);
+    cache.insert(prompt_key(prompt), message, now);
+    cache.save(&path)
+}
+
+/// Current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_key_is_stable() {
+        // Arrange / Act / Assert - known FNV-1a 64-bit values
+        assert_eq!(prompt_key(""), "cbf29ce484222325");
+        assert_eq!(prompt_key("a"), "af63dc4c8601ec8c");
+        assert_eq!(
+            prompt_key("Generate:\n\n+added"),
+            prompt_key("Generate:\n\n+added")
+        );
+    }
+
+    #[test]
+    fn test_prompt_key_differs_for_different_prompts() {
+        // Arrange / Act / Assert
+        assert_ne!(prompt_key("Generate:\n\n+a"), prompt_key("Generate:\n\n+b"));
+    }
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        // Arrange
+        let mut cache = MessageCache::default();
+        cache.insert(prompt_key("prompt"), "feat: cached", 1_000);
+
+        // Act / Assert
+        assert_eq!(
+            cache.get(&prompt_key("prompt"), 60, 1_030),
+            Some("feat: cached")
+        );
+        assert_eq!(cache.get(&prompt_key("other"), 60, 1_030), None);
+    }
+
+    #[test]
+    fn test_cache_entry_expires_after_ttl() {
+        // Arrange
+        let mut cache = MessageCache::default();
+        cache.insert(prompt_key("prompt"), "feat: cached", 1_000);
+
+        // Act / Assert - valid up to the TTL, expired one second later
+        assert!(cache.get(&prompt_key("prompt"), 60, 1_060).is_some());
+        assert!(cache.get(&prompt_key("prompt"), 60, 1_061).is_none());
+    }
+
+    #[test]
+    fn test_prune_drops_expired_entries() {
+        // Arrange
+        let mut cache = MessageCache::default();
+        cache.insert("old".to_string(), "feat: old", 0);
+        cache.insert("new".to_string(), "feat: new", 1_000);
+
+        // Act
+        cache.prune(60, 1_000);
+
+        // Assert
+        assert_eq!(cache.get("old", u64::MAX, 1_000), None);
+        assert_eq!(cache.get("new", 60, 1_000), Some("feat: new"));
+    }
+
+    #[test]
+    fn test_cache_serializes_as_hash_to_entry_map() {
+        // Arrange
+        let mut cache = MessageCache::default();
+        cache.insert("abc".to_string(), "feat: x", 5);
+
+        // Act
+        let json = serde_json::to_string(&cache).unwrap();
+        let parsed: MessageCache = serde_json::from_str(&json).unwrap();
+
+        // Assert
+        assert_eq!(json, r#"{"abc":{"message":"feat: x","created_at":5}}"#);
+        assert_eq!(parsed, cache);
+    }
+}
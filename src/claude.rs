@@ -3,11 +3,218 @@
 //! This module handles communication with Claude AI to generate
 //! commit messages based on git diffs and prompt templates.
 
-use anyhow::{Context, Result};
+use anyhow::Context;
+use std::future::Future;
+use std::io::{ErrorKind, Write};
+use std::process::{Output, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
+use tokio::time::{Duration, sleep};
 
-use crate::config::Config;
-use crate::prompt::build_prompt;
+use crate::backend::{BoxFuture, MessageGenerator};
+use crate::cache;
+use crate::config::{Config, OnOversize, TrimOutput};
+use crate::error::{Error, Result};
+use crate::git::split_diff_by_file;
+use crate::lint::{
+    check_subject_length, validate_allowed_type, validate_conventional, validate_template,
+    wrap_commit_body,
+};
+use crate::log;
+use crate::prompt::{
+    PromptContext, SEPARATOR, build_prompt_with_context, calculate_prompt_size, estimate_tokens,
+    prompt_size_warning, truncate_diff, validate_token_count,
+};
+use crate::trailer::append_trailers;
+
+/// Backend that invokes the Claude Code CLI (`claude -p`)
+///
+/// This is the default [`MessageGenerator`] used by [`generate_message`].
+#[derive(Clone, Debug, Default)]
+pub struct ClaudeCliBackend {
+    /// Executable to run (see [`claude_binary`])
+    pub binary: String,
+    /// Model passed via `--model` (`None` uses the CLI's default model)
+    pub model: Option<String>,
+    /// Additional arguments appended verbatim after the built-in ones
+    pub extra_args: Vec<String>,
+    /// Maximum time to wait for a single invocation (`None` waits indefinitely)
+    pub timeout: Option<Duration>,
+    /// Instructions passed via `--append-system-prompt`, separate from the user prompt
+    pub system_prompt: Option<String>,
+    /// Echo Claude's output to stdout line by line while it is generated
+    pub stream: bool,
+}
+
+impl ClaudeCliBackend {
+    /// Create a backend from the Claude-related configuration fields
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            binary: claude_binary(config),
+            model: config.model.clone(),
+            extra_args: config.extra_args.clone(),
+            timeout: config.timeout_secs.map(Duration::from_secs),
+            system_prompt: config.system_prompt.clone(),
+            stream: config.stream,
+        }
+    }
+
+    /// Arguments passed to the `claude` command
+    ///
+    /// The prompt itself is not included; it is written to stdin.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-p".to_string()];
+        if let Some(model) = &self.model {
+            args.push("--model".to_string());
+            args.push(model.clone());
+        }
+        if let Some(system_prompt) = &self.system_prompt {
+            args.push("--append-system-prompt".to_string());
+            args.push(system_prompt.clone());
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+
+    /// Send a prepared prompt to the Claude CLI and return its output
+    ///
+    /// The prompt is written to `claude -p` through stdin rather than passed as
+    /// an argument, so large diffs never hit the OS argument length limit.
+    async fn run(&self, prompt: &str) -> anyhow::Result<String> {
+        let mut command = Command::new(&self.binary);
+        command.args(self.args());
+
+        let output = if self.stream {
+            let output = run_streaming(command, prompt, self.timeout, |line| {
+                print!("{}", line);
+                let _ = std::io::stdout().flush();
+            })
+            .await?;
+            if !output.stdout.ends_with(b"\n") {
+                println!();
+            }
+            output
+        } else {
+            run_with_input(command, prompt, self.timeout).await?
+        };
+        message_from_output(&output)
+    }
+}
+
+/// Claude exited successfully without printing a message
+#[derive(Debug, thiserror::Error)]
+#[error("Claude returned an empty message\nstderr: {stderr}")]
+struct EmptyMessage {
+    stderr: String,
+}
+
+/// Upper bound on `retry_on_empty` attempts, whatever `retries` is set to
+const MAX_EMPTY_RETRIES: u32 = 3;
+
+/// Call `backend`, calling it again when it returns an empty message
+///
+/// With `config.retry_on_empty`, a message that is empty after sanitizing
+/// (e.g. whitespace or an empty code fence, or the CLI's empty-message
+/// error) is retried right away, without a delay or
+/// warning, `config.retries` times (at least once, at most
+/// [`MAX_EMPTY_RETRIES`]). Otherwise, and once the attempts are used up,
+/// the result is returned as is.
+async fn generate_non_empty(
+    backend: &dyn MessageGenerator,
+    prompt: &str,
+    config: &Config,
+) -> anyhow::Result<String> {
+    let attempts = if config.retry_on_empty {
+        config.retries.clamp(1, MAX_EMPTY_RETRIES)
+    } else {
+        0
+    };
+
+    let mut result = backend.generate(prompt).await;
+    for attempt in 1..=attempts {
+        if !is_empty_message(&result) {
+            break;
+        }
+        log::debug(format!(
+            "Claude returned an empty message, retrying ({}/{})",
+            attempt, attempts
+        ));
+        result = backend.generate(prompt).await;
+    }
+    result
+}
+
+/// Whether a backend result is an empty message rather than a real failure
+fn is_empty_message(result: &anyhow::Result<String>) -> bool {
+    match result {
+        Ok(message) => sanitize_message(message).is_empty(),
+        Err(e) => e.is::<EmptyMessage>(),
+    }
+}
+
+/// Extract the message from the output of `claude -p`
+///
+/// Whitespace is kept; [`sanitize_message_with`] applies `trim_output` later.
+///
+/// # Errors
+///
+/// * The command exited with a non-zero code
+/// * The command succeeded but printed nothing but whitespace
+///   (e.g. a rate limit notice written only to stderr)
+fn message_from_output(output: &Output) -> anyhow::Result<String> {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        anyhow::bail!(
+            "Claude command failed with exit code {:?}\nstderr: {}",
+            output.status.code(),
+            stderr
+        );
+    }
+
+    let message = String::from_utf8_lossy(&output.stdout).to_string();
+    if message.trim().is_empty() {
+        return Err(EmptyMessage {
+            stderr: stderr.trim().to_string(),
+        }
+        .into());
+    }
+    Ok(message)
+}
+
+impl MessageGenerator for ClaudeCliBackend {
+    fn generate<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(self.run(prompt))
+    }
+}
+
+/// A [`ClaudeCliBackend`] driven by a scripted [`CommandRunner`]
+///
+/// [`ClaudeCliBackend`] starts `claude` with tokio so it can time out and
+/// stream, which a [`CommandRunner`] cannot. This test backend shares its
+/// arguments and output handling and only replaces the process launch.
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use crate::runner::CommandRunner;
+
+    /// Runs `cli` through `runner` instead of spawning `claude`
+    pub(crate) struct RunnerBackend<'r> {
+        pub(crate) cli: ClaudeCliBackend,
+        pub(crate) runner: &'r dyn CommandRunner,
+    }
+
+    impl MessageGenerator for RunnerBackend<'_> {
+        fn generate<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+            Box::pin(async move {
+                let output = self
+                    .runner
+                    .run(&self.cli.binary, &self.cli.args(), Some(prompt))
+                    .with_context(|| format!("Failed to execute '{}' command", self.cli.binary))?;
+                message_from_output(&output)
+            })
+        }
+    }
+}
 
 /// Generate a commit message using Claude Code
 ///
@@ -15,6 +222,7 @@ use crate::prompt::build_prompt;
 ///
 /// * `diff` - Git diff content from staged changes
 /// * `config` - Prompt configuration with template
+/// * `context` - Optional prompt sections such as the diff stat
 ///
 /// # Returns
 ///
@@ -22,7 +230,7 @@ use crate::prompt::build_prompt;
 ///
 /// # Errors
 ///
-/// * Prompt size exceeds 1MB (combined diff + prompt template)
+/// * Prompt size exceeds `max_prompt_size` (combined diff + prompt template + context)
 /// * Claude command execution fails
 /// * Claude command returns non-zero exit code
 /// * Unable to parse Claude output
@@ -30,38 +238,1977 @@ use crate::prompt::build_prompt;
 /// # Example
 ///
 /// ```no_run
-/// use claude_commit::{claude::generate_message, config::Config};
+/// use claude_commit::{claude::generate_message, config::Config, prompt::PromptContext};
 ///
 /// # #[tokio::main]
 /// # async fn main() -> anyhow::Result<()> {
 /// let config = Config {
 ///     prompt: "Generate a commit message:".to_string(),
-///     max_prompt_size: 1_000_000,
+///     ..Default::default()
 /// };
 /// let diff = "diff --git a/file.txt b/file.txt\n+new line";
-/// let message = generate_message(diff, &config).await?;
+/// let message = generate_message(diff, &config, &PromptContext::default()).await?;
 /// println!("Message: {}", message);
 /// # Ok(())
 /// # }
 /// ```
-pub async fn generate_message(diff: &str, config: &Config) -> Result<String> {
-    let prompt = build_prompt(diff, &config.prompt, config.max_prompt_size)?;
+pub async fn generate_message(
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+) -> Result<String> {
+    let backend = ClaudeCliBackend::from_config(config);
+    generate_message_with(&backend, diff, config, context).await
+}
+
+/// Generate a commit message using the given backend
+///
+/// Builds and validates the prompt exactly like [`generate_message`], then
+/// hands it to `backend` instead of the Claude CLI (see
+/// [`generate_from_prompt_with`]).
+///
+/// # Errors
+///
+/// * Prompt size exceeds `max_prompt_size`
+/// * The backend fails to generate a message
+pub async fn generate_message_with(
+    backend: &dyn MessageGenerator,
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+) -> Result<String> {
+    let prompt = prepare_prompt_summarized(diff, config, context, |prompt: String| async move {
+        let raw = backend.generate(&prompt).await?;
+        Ok(sanitize_message(&raw))
+    })
+    .await?;
+    generate_from_prompt_with(backend, &prompt, config).await
+}
+
+/// Generate a commit message from a prompt that is already built
+///
+/// Unlike [`generate_message`], the prompt is sent as is (after the size
+/// checks), so a prompt built, truncated or edited by the caller, e.g. with
+/// [`prepare_prompt`], can be reused.
+///
+/// # Errors
+///
+/// * Prompt size exceeds `max_prompt_size` or the estimate exceeds `max_tokens`
+/// * Claude command execution fails
+///
+/// # Example
+///
+/// ```no_run
+/// use claude_commit::{
+///     claude::{generate_from_prompt, prepare_prompt},
+///     config::Config,
+///     prompt::PromptContext,
+/// };
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let config = Config {
+///     prompt: "Generate a commit message:".to_string(),
+///     ..Default::default()
+/// };
+/// let prompt = prepare_prompt("+new line", &config, &PromptContext::default())?;
+/// let message = generate_from_prompt(&prompt, &config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn generate_from_prompt(prompt: &str, config: &Config) -> Result<String> {
+    let backend = ClaudeCliBackend::from_config(config);
+    generate_from_prompt_with(&backend, prompt, config).await
+}
+
+/// Generate a commit message from a prebuilt prompt using the given backend
+///
+/// # Errors
+///
+/// * Prompt size exceeds `max_prompt_size` or the estimate exceeds `max_tokens`
+/// * The backend fails to generate a message
+pub async fn generate_from_prompt_with(
+    backend: &dyn MessageGenerator,
+    prompt: &str,
+    config: &Config,
+) -> Result<String> {
+    validate_prompt(prompt, config)?;
+    generate_checked(config, prompt.to_string(), |prompt| async move {
+        let raw = generate_non_empty(backend, &prompt, config).await?;
+        Ok(sanitize_message_with(&raw, config.trim_output))
+    })
+    .await
+}
+
+/// Check a built prompt against `max_prompt_size` and `max_tokens`
+///
+/// The system prompt counts toward `max_prompt_size`, as in [`prepare_prompt`].
+///
+/// # Errors
+///
+/// * Prompt size exceeds `max_prompt_size`
+/// * Estimated token count exceeds `max_tokens`
+fn validate_prompt(prompt: &str, config: &Config) -> Result<()> {
+    let system_bytes = config.system_prompt.as_deref().map_or(0, str::len);
+    let max_size = config.max_prompt_size.saturating_sub(system_bytes);
+    if prompt.len() > max_size {
+        return Err(Error::PromptTooLarge {
+            actual: prompt.len(),
+            max: max_size,
+        });
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        validate_token_count(prompt, max_tokens)?;
+    }
+    Ok(())
+}
+
+/// Generate a commit message, retrying failed Claude invocations
+///
+/// The prompt is built and validated once up front, so deterministic
+/// failures such as an oversized prompt are returned immediately. Only
+/// the Claude invocation itself is retried, waiting `base_delay`,
+/// `2 * base_delay`, `4 * base_delay`, ... between attempts. With
+/// `summarize_large`, the per-file summary calls are retried the same way.
+///
+/// # Arguments
+///
+/// * `diff` - Git diff content from staged changes
+/// * `config` - Prompt configuration with template
+/// * `context` - Optional prompt sections such as the diff stat
+/// * `retries` - Number of retries after the first failed attempt
+/// * `base_delay` - Delay before the first retry
+///
+/// # Errors
+///
+/// * Prompt size exceeds `max_prompt_size` (never retried)
+/// * Claude command still fails after all retries (last error is returned)
+pub async fn generate_message_with_retries(
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+    retries: u32,
+    base_delay: Duration,
+) -> Result<String> {
+    let backend = ClaudeCliBackend::from_config(config);
+    generate_retrying(&backend, diff, config, context, retries, base_delay).await
+}
+
+/// [`generate_message_with_retries`] with a custom backend
+///
+/// Empty responses are retried only by [`generate_non_empty`] when
+/// `retry_on_empty` is set, so the two retry counts never multiply.
+async fn generate_retrying(
+    backend: &dyn MessageGenerator,
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+    retries: u32,
+    base_delay: Duration,
+) -> Result<String> {
+    let retryable = |e: &anyhow::Error| !(config.retry_on_empty && e.is::<EmptyMessage>());
+    let generate = |prompt: String| async move {
+        let raw = retry_with_backoff(retries, base_delay, retryable, || {
+            generate_non_empty(backend, &prompt, config)
+        })
+        .await?;
+        Ok(sanitize_message_with(&raw, config.trim_output))
+    };
+    let prompt = prepare_prompt_summarized(diff, config, context, generate).await?;
+    generate_checked(config, prompt, generate).await
+}
+
+/// Generate a message from `prompt` and apply the configured output checks
+///
+/// Runs the lint check (see [`generate_linted`]), then enforces
+/// `config.max_subject_length`: a subject that is too long triggers one
+/// regeneration with an explicit length instruction appended to the prompt.
+///
+/// With `config.use_cache`, a fresh cached message for the same prompt and
+/// generation settings (see [`cache_input`]) is returned without calling
+/// `generate`, and new messages are cached.
+/// The body is wrapped at `config.wrap_body` and `config.trailers` are
+/// appended last, so the cache holds the bare message.
+///
+/// # Errors
+///
+/// * `generate` fails
+/// * The subject is still too long after the regeneration
+async fn generate_checked<F, Fut>(config: &Config, prompt: String, generate: F) -> Result<String>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let cache_input = cache_input(config, &prompt);
+    if config.use_cache
+        && let Some(message) = cache::lookup(&cache_input, config.cache_ttl_secs)
+    {
+        log::info("Using the cached message for this diff (--no-cache to regenerate)");
+        return Ok(finish_message(config, &message));
+    }
+
+    let message = generate_subject_checked(config, &prompt, generate).await?;
+
+    if config.use_cache
+        && let Err(e) = cache::store(&cache_input, &message, config.cache_ttl_secs)
+    {
+        log::warn(format!("failed to update the message cache: {}", e));
+    }
+    Ok(finish_message(config, &message))
+}
+
+/// Text the message cache is keyed on
+///
+/// The prompt alone is not enough: the same prompt gives a different message
+/// with another binary, model, system prompt or extra arguments, and the
+/// output settings and checks decide which message is kept. All of them are
+/// prepended to the prompt.
+fn cache_input(config: &Config, prompt: &str) -> String {
+    let settings = serde_json::json!({
+        "claude_binary": claude_binary(config),
+        "model": config.model,
+        "system_prompt": config.system_prompt,
+        "extra_args": config.extra_args,
+        "trim_output": format!("{:?}", config.trim_output),
+        "lint": config.lint,
+        "lint_retries": config.lint_retries,
+        "check_template": config.check_template,
+        "message_template": config.message_template,
+        "allowed_types": config.allowed_types,
+        "max_subject_length": config.max_subject_length,
+    });
+    format!("{}\n{}", settings, prompt)
+}
+
+/// Apply `config.wrap_body`, then append `config.trailers`
+fn finish_message(config: &Config, message: &str) -> String {
+    let message = match config.wrap_body {
+        Some(width) => wrap_commit_body(message, width),
+        None => message.to_string(),
+    };
+    append_trailers(&message, &config.trailers)
+}
+
+/// Generate a lint-checked message and enforce `config.max_subject_length`
+async fn generate_subject_checked<F, Fut>(
+    config: &Config,
+    prompt: &str,
+    generate: F,
+) -> Result<String>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let message = generate_linted(config, || generate(prompt.to_string())).await?;
+    let Some(max) = config.max_subject_length else {
+        return Ok(message);
+    };
+    if check_subject_length(&message, max).is_ok() {
+        return Ok(message);
+    }
+
+    log::warn(format!(
+        "subject is longer than {} characters, regenerating with a stricter instruction",
+        max
+    ));
+    let strict_prompt = format!("{}{}{}", prompt, SEPARATOR, subject_length_instruction(max));
+    let message = generate_linted(config, || generate(strict_prompt.clone())).await?;
+    check_subject_length(&message, max).map_err(|e| {
+        Error::ClaudeFailed(anyhow::Error::new(e).context("Generated subject is too long"))
+    })?;
+    Ok(message)
+}
+
+/// Instruction appended to the prompt when a subject came back too long
+fn subject_length_instruction(max: usize) -> String {
+    format!(
+        "The first line (subject) of the commit message must be at most {} characters long.",
+        max
+    )
+}
+
+/// Run `generate`, then check the message against `lint`, `check_template` and `allowed_types`
+///
+/// Violations are printed as warnings. The message is regenerated up to
+/// `config.lint_retries` times; if it still fails, the last message is
+/// returned so the user can fix it by hand.
+async fn generate_linted<F, Fut>(config: &Config, mut generate: F) -> Result<String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let mut message = non_empty_message(generate().await)?;
+
+    let mut attempt = 0;
+    while let Some(problem) = message_check_failure(config, &message) {
+        log::warn(format!("generated message {}", problem));
+        if attempt >= config.lint_retries {
+            break;
+        }
+        attempt += 1;
+        log::info(format!(
+            "Regenerating ({}/{})...",
+            attempt, config.lint_retries
+        ));
+        message = non_empty_message(generate().await)?;
+    }
+    Ok(message)
+}
+
+/// Take the message out of a sanitized backend result, rejecting an empty one
+///
+/// A reply such as an empty code fence gets past the CLI's output check but
+/// sanitizes to nothing; it must never be linted, cached or committed.
+fn non_empty_message(result: anyhow::Result<String>) -> Result<String> {
+    let message = result.map_err(Error::ClaudeFailed)?;
+    if message.trim().is_empty() {
+        return Err(Error::ClaudeFailed(
+            EmptyMessage {
+                stderr: String::new(),
+            }
+            .into(),
+        ));
+    }
+    Ok(message)
+}
+
+/// Describe why `message` fails the enabled checks, or `None` if it passes
+fn message_check_failure(config: &Config, message: &str) -> Option<String> {
+    if config.lint
+        && let Err(violations) = validate_conventional(message)
+    {
+        return Some(format!(
+            "does not follow Conventional Commits:\n{}",
+            violations
+        ));
+    }
+    if config.check_template
+        && let Some(template) = &config.message_template
+        && let Err(violations) = validate_template(message, template)
+    {
+        return Some(format!("does not match message_template:\n{}", violations));
+    }
+    if let Err(violation) = validate_allowed_type(message, &config.allowed_types) {
+        return Some(format!(
+            "uses a commit type that is not allowed: {}",
+            violation
+        ));
+    }
+    None
+}
+
+/// Remove an outer Markdown code fence from a generated message
+///
+/// Claude sometimes wraps the whole message in a fence such as
+/// ```` ```text ... ``` ````. When the trimmed message both starts with an
+/// opening fence line (optionally followed by a language tag) and ends with
+/// a closing fence line, the fences are removed. Backticks anywhere else,
+/// including fenced blocks inside the body, are left alone.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::claude::sanitize_message;
+///
+/// let raw = "```text\nfeat: add `--json` flag\n```\n";
+/// assert_eq!(sanitize_message(raw), "feat: add `--json` flag");
+/// ```
+pub fn sanitize_message(raw: &str) -> String {
+    sanitize_message_with(raw, TrimOutput::Trim)
+}
+
+/// Remove an outer code fence, then whitespace according to `trim`
+///
+/// The fence is detected on the trimmed message as in [`sanitize_message`];
+/// `trim` then applies to the fence's content, or to the whole message when
+/// there is no fence.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::{claude::sanitize_message_with, config::TrimOutput};
+///
+/// let raw = "\n  indented body\n\n";
+/// assert_eq!(sanitize_message_with(raw, TrimOutput::TrimEnd), "\n  indented body");
+/// ```
+pub fn sanitize_message_with(raw: &str, trim: TrimOutput) -> String {
+    let text = strip_outer_fence(raw.trim()).unwrap_or(raw);
+    match trim {
+        TrimOutput::Trim => text.trim(),
+        TrimOutput::TrimEnd => text.trim_end(),
+        TrimOutput::None => text,
+    }
+    .to_string()
+}
+
+/// Return the content between an outer opening and closing fence, if any
+fn strip_outer_fence(text: &str) -> Option<&str> {
+    let (opening, body) = text.strip_prefix("```")?.split_once('\n')?;
+    let tag = opening.trim();
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '.'))
+    {
+        return None;
+    }
+
+    let inner = body.strip_suffix("```")?;
+    if inner.is_empty() || inner.ends_with('\n') {
+        Some(inner)
+    } else {
+        None
+    }
+}
+
+/// Generate several candidate commit messages
+///
+/// Invokes Claude `count` times (at least once), each with the configured
+/// retries, and returns the messages in generation order. The message cache
+/// is only used for a single candidate.
+///
+/// # Errors
+///
+/// * Prompt size exceeds `max_prompt_size`
+/// * Any Claude invocation fails after its retries
+pub async fn generate_candidates(
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+    count: usize,
+) -> Result<Vec<String>> {
+    let uncached;
+    let config = if count > 1 && config.use_cache {
+        uncached = Config {
+            use_cache: false,
+            ..config.clone()
+        };
+        &uncached
+    } else {
+        config
+    };
+
+    let mut messages = Vec::with_capacity(count.max(1));
+    for _ in 0..count.max(1) {
+        let message = generate_message_with_retries(
+            diff,
+            config,
+            context,
+            config.retries,
+            Duration::from_millis(config.retry_delay_ms),
+        )
+        .await?;
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// The diff that generation will actually send for `diff`
+///
+/// With `on_oversize = "truncate"` this is the truncation [`prepare_prompt`]
+/// applies; otherwise, or when the diff fits, `diff` is returned unchanged.
+/// With `summarize_large` an oversized diff is summarized rather than
+/// truncated, so it is also returned unchanged.
+///
+/// # Returns
+///
+/// * `(String, bool)` - The (possibly truncated) diff and whether it was truncated
+pub fn diff_for_prompt(diff: &str, config: &Config, context: &PromptContext) -> (String, bool) {
+    if config.on_oversize != OnOversize::Truncate || config.summarize_large {
+        return (diff.to_string(), false);
+    }
+    truncate_to_prompt(diff, config, context)
+}
+
+/// Bytes available for the user prompt once the system prompt is accounted for
+fn user_prompt_limit(config: &Config) -> usize {
+    let system_bytes = config.system_prompt.as_deref().map_or(0, str::len);
+    config.max_prompt_size.saturating_sub(system_bytes)
+}
+
+/// Truncate `diff` to the room left after the template and context sections
+fn truncate_to_prompt(diff: &str, config: &Config, context: &PromptContext) -> (String, bool) {
+    let overhead = calculate_prompt_size("", &config.prompt, context);
+    truncate_diff(diff, user_prompt_limit(config).saturating_sub(overhead))
+}
+
+/// Build and validate the prompt, truncating the diff first if configured
+///
+/// With `on_oversize = "truncate"` the diff is cut to whatever room is left
+/// after the template and context sections, and a warning is printed.
+/// When `max_tokens` is set, the estimated token count is checked as well.
+/// A prompt above `warn_threshold` of the limit prints a warning.
+///
+/// The system prompt is sent separately but counts toward `max_prompt_size`,
+/// so the returned user prompt is limited to what remains after it.
+///
+/// # Errors
+///
+/// * Prompt exceeds `max_prompt_size` (with `on_oversize = "error"`)
+/// * Estimated token count exceeds `max_tokens`
+pub fn prepare_prompt(diff: &str, config: &Config, context: &PromptContext) -> Result<String> {
+    let max_size = user_prompt_limit(config);
+
+    let prompt = if config.on_oversize == OnOversize::Truncate {
+        let (truncated, was_truncated) = truncate_to_prompt(diff, config, context);
+        if was_truncated {
+            log::warn(format!(
+                "diff truncated from {} to {} bytes to fit max_prompt_size ({} bytes)",
+                diff.len(),
+                truncated.len(),
+                config.max_prompt_size
+            ));
+        }
+        build_prompt_with_context(&truncated, &config.prompt, context, max_size)?
+    } else {
+        build_prompt_with_context(diff, &config.prompt, context, max_size)?
+    };
+    if let Some(warning) = prompt_size_warning(prompt.len(), max_size, config.warn_threshold) {
+        log::warn(warning);
+    }
+
+    if let Some(max_tokens) = config.max_tokens {
+        log::debug(format!(
+            "Estimated prompt tokens: ~{} (max_tokens: {})",
+            estimate_tokens(&prompt),
+            max_tokens
+        ));
+        validate_token_count(&prompt, max_tokens)?;
+    }
+
+    Ok(prompt)
+}
+
+/// Instruction sent with each file's diff when summarizing an oversized diff
+const FILE_SUMMARY_PROMPT: &str = "Summarize the following changes to a single file in a few \
+     short bullet points, for use in a commit message. Output only the bullet points.";
+
+/// Heading placed before the per-file summaries in the final prompt
+const SUMMARIES_HEADING: &str =
+    "The diff was too large to include. Summaries of the changes in each file:";
+
+/// One file's section of a diff
+#[derive(Debug, PartialEq, Eq)]
+struct FileDiff<'a> {
+    path: String,
+    diff: &'a str,
+}
+
+/// Split a diff into per-file sections, each paired with the file's path
+///
+/// Sections start at `diff --git` headers; any text before the first
+/// header is dropped.
+fn split_file_diffs(diff: &str) -> Vec<FileDiff<'_>> {
+    split_diff_by_file(diff)
+        .into_iter()
+        .filter_map(|section| {
+            let header = section.lines().next()?.strip_prefix("diff --git ")?;
+            let path = header
+                .rsplit_once(" b/")
+                .map_or(header, |(_, path)| path)
+                .to_string();
+            Some(FileDiff {
+                path,
+                diff: section,
+            })
+        })
+        .collect()
+}
+
+/// Build the prompt like [`prepare_prompt`], summarizing an oversized diff first
+///
+/// With `config.summarize_large` and a prompt above `max_prompt_size`, each
+/// file's diff is summarized with its own `generate` call (truncated to fit
+/// if needed), and the final prompt is built from the summaries instead of
+/// the diff.
+///
+/// # Errors
+///
+/// * A summary call fails
+/// * The prompt, even with summaries, exceeds `max_prompt_size` or `max_tokens`
+async fn prepare_prompt_summarized<F, Fut>(
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+    generate: F,
+) -> Result<String>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let system_bytes = config.system_prompt.as_deref().map_or(0, str::len);
+    let size = calculate_prompt_size(diff, &config.prompt, context) + system_bytes;
+    if !config.summarize_large || size <= config.max_prompt_size {
+        return prepare_prompt(diff, config, context);
+    }
+
+    let files = split_file_diffs(diff);
+    log::info(format!(
+        "Diff exceeds max_prompt_size ({} bytes); summarizing {} file(s) first",
+        config.max_prompt_size,
+        files.len()
+    ));
+    let budget = config
+        .max_prompt_size
+        .saturating_sub(system_bytes + FILE_SUMMARY_PROMPT.len() + SEPARATOR.len());
+
+    let mut summaries = String::from(SUMMARIES_HEADING);
+    for file in files {
+        log::debug(format!("Summarizing {}", file.path));
+        let (section, _) = truncate_diff(file.diff, budget);
+        let prompt = format!("{}{}{}", FILE_SUMMARY_PROMPT, SEPARATOR, section);
+        let summary = generate(prompt).await.map_err(Error::ClaudeFailed)?;
+        summaries.push_str(&format!("\n\n### {}\n{}", file.path, summary.trim()));
+    }
+    summaries.push('\n');
+    prepare_prompt(&summaries, config, context)
+}
+
+/// Run `operation` until it succeeds or `retries` retries are exhausted
+///
+/// The delay doubles after each failed attempt, starting at `base_delay`.
+/// Errors for which `retryable` returns `false` are returned right away.
+async fn retry_with_backoff<T, F, Fut>(
+    retries: u32,
+    base_delay: Duration,
+    retryable: impl Fn(&anyhow::Error) -> bool,
+    mut operation: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < retries && retryable(&e) => {
+                let delay = base_delay.saturating_mul(2u32.saturating_pow(attempt));
+                log::warn(format!(
+                    "Claude command failed (attempt {}/{}): {:#}. Retrying in {:?}...",
+                    attempt + 1,
+                    retries + 1,
+                    e,
+                    delay
+                ));
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Environment variable that overrides the Claude CLI executable
+pub const CLAUDE_BINARY_ENV: &str = "CLAUDE_BINARY";
+
+/// Name of the Claude CLI executable used when nothing overrides it, looked up in PATH
+const DEFAULT_CLAUDE_BINARY: &str = "claude";
+
+/// Resolve the Claude CLI executable from the environment and config values
+///
+/// Precedence is environment variable, then `claude_binary` in the config,
+/// then `claude`. Empty values are ignored.
+fn claude_binary_from(env_value: Option<String>, config_value: Option<&str>) -> String {
+    env_value
+        .filter(|v| !v.trim().is_empty())
+        .or_else(|| {
+            config_value
+                .filter(|v| !v.trim().is_empty())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| DEFAULT_CLAUDE_BINARY.to_string())
+}
+
+/// Get the Claude CLI executable, honoring `CLAUDE_BINARY` and `config.claude_binary`
+pub fn claude_binary(config: &Config) -> String {
+    claude_binary_from(
+        std::env::var(CLAUDE_BINARY_ENV).ok(),
+        config.claude_binary.as_deref(),
+    )
+}
+
+/// Where to get the Claude CLI when it is not installed
+const CLAUDE_INSTALL_URL: &str = "https://github.com/anthropics/claude-code";
+
+/// Describe a failure to start `command`
+///
+/// A missing executable gets an actionable message; other failures (e.g.
+/// permission denied) keep the underlying OS error.
+fn spawn_error(command: &Command, error: std::io::Error) -> anyhow::Error {
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+    if error.kind() == ErrorKind::NotFound {
+        anyhow::anyhow!(
+            "'{}' command not found. Install the Claude CLI ({}) and make sure it is in PATH, \
+             or set {} / `claude_binary` to its location",
+            program,
+            CLAUDE_INSTALL_URL,
+            CLAUDE_BINARY_ENV
+        )
+    } else {
+        anyhow::Error::new(error).context(format!("Failed to execute '{}' command", program))
+    }
+}
+
+/// Run a command with `input` on stdin, killing it if it exceeds `timeout`
+///
+/// Stdin is written concurrently with collecting stdout/stderr so a child
+/// producing output before consuming all input cannot deadlock. With no
+/// timeout this waits indefinitely.
+async fn run_with_input(
+    mut command: Command,
+    input: &str,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Output> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Dropping the pending future on timeout kills the child process
+        .kill_on_drop(true);
+
+    let mut child = command.spawn().map_err(|e| spawn_error(&command, e))?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Failed to open stdin of child process")?;
+
+    let run = async {
+        let write = async {
+            let result = stdin.write_all(input.as_bytes()).await;
+            drop(stdin);
+            result
+        };
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        match written {
+            // The child may legitimately exit without reading all of its input
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+                Err(e).context("Failed to write prompt to stdin")
+            }
+            _ => output.context("Failed to read output of child process"),
+        }
+    };
+
+    with_timeout(timeout, run).await
+}
+
+/// Run a command with `input` on stdin, passing each stdout line to `on_line`
+///
+/// Behaves like [`run_with_input`], but reads stdout incrementally so the
+/// output can be shown while the command is still running. The returned
+/// [`Output`] contains the full stdout, unmodified.
+async fn run_streaming(
+    mut command: Command,
+    input: &str,
+    timeout: Option<Duration>,
+    mut on_line: impl FnMut(&str),
+) -> anyhow::Result<Output> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn().map_err(|e| spawn_error(&command, e))?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Failed to open stdin of child process")?;
+    let stdout = child
+        .stdout
+        .take()
+        .context("Failed to open stdout of child process")?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .context("Failed to open stderr of child process")?;
+
+    let run = async {
+        let write = async {
+            let result = stdin.write_all(input.as_bytes()).await;
+            drop(stdin);
+            result
+        };
+        let read_stdout = async {
+            let mut reader = BufReader::new(stdout);
+            let mut collected = Vec::new();
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).await? > 0 {
+                on_line(&String::from_utf8_lossy(&line));
+                collected.append(&mut line);
+            }
+            Ok::<_, std::io::Error>(collected)
+        };
+        let read_stderr = async {
+            let mut collected = Vec::new();
+            stderr.read_to_end(&mut collected).await.map(|_| collected)
+        };
+        let (written, stdout, stderr) = tokio::join!(write, read_stdout, read_stderr);
+        if let Err(e) = written
+            && e.kind() != ErrorKind::BrokenPipe
+        {
+            return Err(e).context("Failed to write prompt to stdin");
+        }
+        Ok(Output {
+            status: child
+                .wait()
+                .await
+                .context("Failed to wait for child process")?,
+            stdout: stdout.context("Failed to read output of child process")?,
+            stderr: stderr.context("Failed to read output of child process")?,
+        })
+    };
+
+    with_timeout(timeout, run).await
+}
+
+/// Await `run`, failing once `timeout` elapses (dropping it kills the child)
+async fn with_timeout(
+    timeout: Option<Duration>,
+    run: impl Future<Output = anyhow::Result<Output>>,
+) -> anyhow::Result<Output> {
+    let Some(limit) = timeout else {
+        return run.await;
+    };
+
+    match tokio::time::timeout(limit, run).await {
+        Ok(output) => output,
+        Err(_) => anyhow::bail!(
+            "Claude command timed out after {} seconds (timeout_secs)",
+            limit.as_secs_f64()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::Mutex;
+
+    /// Backend that records the prompt it receives and returns a fixed message
+    struct RecordingBackend {
+        prompt: Mutex<Option<String>>,
+    }
+
+    impl MessageGenerator for RecordingBackend {
+        fn generate<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+            *self.prompt.lock().unwrap() = Some(prompt.to_string());
+            Box::pin(async { Ok("feat: from fake backend".to_string()) })
+        }
+    }
+
+    /// Backend returning canned responses in order, repeating the last one
+    struct ScriptedBackend {
+        responses: Vec<String>,
+        calls: Mutex<usize>,
+    }
+
+    impl ScriptedBackend {
+        fn new(responses: &[&str]) -> Self {
+            Self {
+                responses: responses.iter().map(|r| r.to_string()).collect(),
+                calls: Mutex::new(0),
+            }
+        }
+    }
+
+    impl MessageGenerator for ScriptedBackend {
+        fn generate<'a>(&'a self, _prompt: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+            let mut calls = self.calls.lock().unwrap();
+            let response = self.responses[(*calls).min(self.responses.len() - 1)].clone();
+            *calls += 1;
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_lint_regenerates_invalid_message() {
+        // Arrange - first response violates the format, second is valid
+        let backend = ScriptedBackend::new(&["Added a feature", "feat: add a feature"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            lint: true,
+            lint_retries: 2,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(message, "feat: add a feature");
+        assert_eq!(*backend.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_check_template_regenerates() {
+        // Arrange - only the second response matches the template
+        let backend = ScriptedBackend::new(&["feat: add login", "[auth] add login"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            message_template: Some("[{component}] {summary}".to_string()),
+            check_template: true,
+            lint_retries: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(message, "[auth] add login");
+        assert_eq!(*backend.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_lint_keeps_last_message_after_retries() {
+        // Arrange - never valid
+        let backend = ScriptedBackend::new(&["Added a feature"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            lint: true,
+            lint_retries: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert - warned, retried once, then returned as-is
+        assert_eq!(message, "Added a feature");
+        assert_eq!(*backend.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_lint_disabled_does_not_regenerate() {
+        // Arrange
+        let backend = ScriptedBackend::new(&["Added a feature", "feat: add a feature"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            lint_retries: 2,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(message, "Added a feature");
+        assert_eq!(*backend.calls.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_regenerates_long_subject() {
+        // Arrange - first subject is 90 characters, second fits
+        let long = format!("feat: {}", "a".repeat(84));
+        let backend = ScriptedBackend::new(&[&long, "feat: short subject"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_subject_length: Some(50),
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(message, "feat: short subject");
+        assert_eq!(*backend.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_long_subject_errors_after_regeneration() {
+        // Arrange - 40 CJK characters (120 bytes) against a 30-character limit
+        let long = "あ".repeat(40);
+        let backend = ScriptedBackend::new(&[&long]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_subject_length: Some(30),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            generate_message_with(&backend, "+added", &config, &PromptContext::default()).await;
+
+        // Assert - measured in characters, regenerated once, then rejected
+        let err = format!("{:#}", result.unwrap_err());
+        assert!(err.contains("40 characters"));
+        assert_eq!(*backend.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_cjk_subject_within_char_limit() {
+        // Arrange - 20 characters but 60 bytes, limit 50
+        let backend = ScriptedBackend::new(&["機能追加：ユーザー認証のログイン画面を実装した"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_subject_length: Some(50),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            generate_message_with(&backend, "+added", &config, &PromptContext::default()).await;
+
+        // Assert - accepted without regeneration
+        assert!(result.is_ok());
+        assert_eq!(*backend.calls.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_respects_raised_size_limit() {
+        // Arrange - 2MB diff, above the 1MB default but under the configured limit
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 3_000_000,
+            ..Default::default()
+        };
+        let diff = "+".repeat(2_000_000);
+
+        // Act
+        let result =
+            generate_message_with(&backend, &diff, &config, &PromptContext::default()).await;
+
+        // Assert - config limit is used instead of the default
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_rejects_over_default_limit() {
+        // Arrange - same 2MB diff with the default limit
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            ..Default::default()
+        };
+        let diff = "+".repeat(2_000_000);
+
+        // Act
+        let result =
+            generate_message_with(&backend, &diff, &config, &PromptContext::default()).await;
+
+        // Assert - rejected before the backend is called
+        assert!(result.is_err());
+        assert!(backend.prompt.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_truncates_when_configured() {
+        // Arrange - diff far over the limit with truncation enabled
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 200,
+            on_oversize: OnOversize::Truncate,
+            ..Default::default()
+        };
+        let diff = "+added line\n".repeat(100);
+
+        // Act
+        let result =
+            generate_message_with(&backend, &diff, &config, &PromptContext::default()).await;
+
+        // Assert - backend receives a prompt within the limit, with the marker
+        assert!(result.is_ok());
+        let prompt = backend.prompt.lock().unwrap().clone().unwrap();
+        assert!(prompt.len() <= 200);
+        assert!(prompt.contains("[diff truncated,"));
+    }
+
+    #[test]
+    fn test_diff_for_prompt_matches_prepared_prompt() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 200,
+            on_oversize: OnOversize::Truncate,
+            ..Default::default()
+        };
+        let diff = "+added line\n".repeat(100);
+        let context = PromptContext::default();
+
+        // Act
+        let (sent, truncated) = diff_for_prompt(&diff, &config, &context);
+        let prompt = prepare_prompt(&diff, &config, &context).unwrap();
+
+        // Assert - the reported diff is the one in the prompt
+        assert!(truncated);
+        assert!(sent.len() < diff.len());
+        assert!(prompt.ends_with(&sent));
+    }
+
+    #[test]
+    fn test_diff_for_prompt_unchanged_without_truncate() {
+        // Arrange - over the limit, but oversize prompts are an error
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 200,
+            ..Default::default()
+        };
+        let diff = "+added line\n".repeat(100);
+
+        // Act
+        let (sent, truncated) = diff_for_prompt(&diff, &config, &PromptContext::default());
+
+        // Assert
+        assert!(!truncated);
+        assert_eq!(sent, diff);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_custom_backend() {
+        // Arrange
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert - backend output is returned and receives the built prompt
+        assert_eq!(message, "feat: from fake backend");
+        assert_eq!(
+            backend.prompt.lock().unwrap().as_deref(),
+            Some("Generate:\n\n+added")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_succeeds_after_failures() {
+        // Arrange - operation fails twice, then succeeds
+        let attempts = Cell::new(0);
+
+        // Act
+        let result = retry_with_backoff(
+            3,
+            Duration::ZERO,
+            |_| true,
+            || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    if attempt < 3 {
+                        anyhow::bail!("transient failure {}", attempt);
+                    }
+                    Ok("feat: done".to_string())
+                }
+            },
+        )
+        .await;
+
+        // Assert - stops retrying as soon as it succeeds
+        assert_eq!(result.unwrap(), "feat: done");
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_gives_up_after_retries() {
+        // Arrange - operation always fails
+        let attempts = Cell::new(0);
+
+        // Act
+        let result: anyhow::Result<String> = retry_with_backoff(
+            2,
+            Duration::ZERO,
+            |_| true,
+            || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move { anyhow::bail!("failure {}", attempt) }
+            },
+        )
+        .await;
+
+        // Assert - first attempt + 2 retries, last error is returned
+        assert_eq!(attempts.get(), 3);
+        assert_eq!(result.unwrap_err().to_string(), "failure 3");
+    }
+
+    /// Backend that always fails with the CLI's empty-message error
+    struct EmptyBackend {
+        calls: Mutex<usize>,
+    }
+
+    impl MessageGenerator for EmptyBackend {
+        fn generate<'a>(&'a self, _prompt: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+            *self.calls.lock().unwrap() += 1;
+            Box::pin(async {
+                Err(EmptyMessage {
+                    stderr: String::new(),
+                }
+                .into())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_retries_do_not_multiply_with_backoff() {
+        // Arrange - every call comes back empty
+        let backend = EmptyBackend {
+            calls: Mutex::new(0),
+        };
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            retry_on_empty: true,
+            retries: 3,
+            ..Default::default()
+        };
+
+        // Act
+        let result = generate_retrying(
+            &backend,
+            "+added",
+            &config,
+            &PromptContext::default(),
+            3,
+            Duration::ZERO,
+        )
+        .await;
+
+        // Assert - the first call plus MAX_EMPTY_RETRIES, not (1 + 3) * 4
+        assert!(result.is_err());
+        assert_eq!(
+            *backend.calls.lock().unwrap(),
+            1 + MAX_EMPTY_RETRIES as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_responses_use_backoff_without_retry_on_empty() {
+        // Arrange
+        let backend = EmptyBackend {
+            calls: Mutex::new(0),
+        };
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let result = generate_retrying(
+            &backend,
+            "+added",
+            &config,
+            &PromptContext::default(),
+            2,
+            Duration::ZERO,
+        )
+        .await;
+
+        // Assert - only the backoff loop retries
+        assert!(result.is_err());
+        assert_eq!(*backend.calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_retries_does_not_retry_oversized_prompt() {
+        // Arrange - prompt exceeds the limit before Claude is ever invoked
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 10,
+            ..Default::default()
+        };
+
+        // Act
+        let result = generate_message_with_retries(
+            &"+".repeat(100),
+            &config,
+            &PromptContext::default(),
+            5,
+            Duration::from_secs(60),
+        )
+        .await;
+
+        // Assert - fails immediately with the size error (no 60s backoff)
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("exceeds maximum allowed size"));
+    }
+
+    #[tokio::test]
+    async fn test_run_with_input_missing_binary() {
+        // Arrange
+        let command = Command::new("claude_commit_nonexistent_binary");
+
+        // Act
+        let result = run_with_input(command, "", None).await;
+
+        // Assert - names the binary, PATH and where to install it
+        let error_msg = format!("{:#}", result.unwrap_err());
+        assert!(error_msg.contains("'claude_commit_nonexistent_binary' command not found"));
+        assert!(error_msg.contains("PATH"));
+        assert!(error_msg.contains(CLAUDE_INSTALL_URL));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_with_input_spawn_error_other_than_not_found() {
+        // Arrange - a directory exists but cannot be executed
+        let command = Command::new(std::env::temp_dir());
 
-    let output = Command::new("claude")
-        .args(["-p", &prompt])
-        .output()
+        // Act
+        let result = run_with_input(command, "", None).await;
+
+        // Assert - reported as an execution failure, not as a missing binary
+        let error_msg = format!("{:#}", result.unwrap_err());
+        assert!(error_msg.contains("Failed to execute"));
+        assert!(!error_msg.contains("not found"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_with_input_kills_slow_command() {
+        // Arrange - fake command that sleeps longer than the timeout
+        let mut command = Command::new("sleep");
+        command.arg("5");
+
+        // Act
+        let started = std::time::Instant::now();
+        let result = run_with_input(command, "", Some(Duration::from_millis(100))).await;
+
+        // Assert - returns promptly with an error naming the limit
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("timed out after 0.1 seconds"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_streaming_passes_each_line() {
+        // Arrange - `cat` echoes the prompt back, the last line unterminated
+        let command = Command::new("cat");
+        let mut lines = Vec::new();
+
+        // Act
+        let output = run_streaming(command, "feat: stream\n\n- body", None, |line| {
+            lines.push(line.to_string())
+        })
         .await
-        .context(
-            "Failed to execute 'claude' command. Make sure Claude CLI is installed and in PATH",
-        )?;
+        .unwrap();
 
-    if !output.status.success() {
-        anyhow::bail!(
-            "Claude command failed with exit code {:?}\nstderr: {}",
-            output.status.code(),
-            String::from_utf8_lossy(&output.stderr)
+        // Assert - lines arrive as read, and the full output is kept for the message
+        assert_eq!(lines, vec!["feat: stream\n", "\n", "- body"]);
+        assert_eq!(
+            message_from_output(&output).unwrap(),
+            "feat: stream\n\n- body"
         );
     }
 
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_streaming_kills_slow_command() {
+        // Arrange
+        let mut command = Command::new("sleep");
+        command.arg("5");
+
+        // Act
+        let result = run_streaming(command, "", Some(Duration::from_millis(100)), |_| {}).await;
+
+        // Assert
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("timed out after 0.1 seconds"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_with_input_pipes_prompt_through_stdin() {
+        // Arrange - `cat` echoes stdin, standing in for `claude -p`
+        let command = Command::new("cat");
+        let prompt = "Generate:\n\n".to_string() + &"+line\n".repeat(100_000);
+
+        // Act - prompt is larger than a pipe buffer
+        let output = run_with_input(command, &prompt, Some(Duration::from_secs(10)))
+            .await
+            .unwrap();
+
+        // Assert - the whole prompt arrives intact
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), prompt);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_with_input_child_ignoring_stdin() {
+        // Arrange - command exits without reading its input
+        let mut command = Command::new("echo");
+        command.arg("feat: done");
+
+        // Act
+        let output = run_with_input(command, &"x".repeat(1_000_000), None)
+            .await
+            .unwrap();
+
+        // Assert - broken pipe on stdin is not treated as a failure
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "feat: done\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_message_from_output_rejects_whitespace_only_output() {
+        // Arrange - fake command that exits 0 but prints only whitespace
+        let mut command = Command::new("sh");
+        command.args(["-c", "printf '  \\n\\n'; echo 'rate limit reached' >&2"]);
+        let output = run_with_input(command, "", None).await.unwrap();
+
+        // Act
+        let result = message_from_output(&output);
+
+        // Assert - the error explains the problem and includes stderr
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("Claude returned an empty message"));
+        assert!(error_msg.contains("rate limit reached"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_message_from_output_keeps_whitespace() {
+        // Arrange
+        let mut command = Command::new("echo");
+        command.arg("  feat: done  ");
+        let output = run_with_input(command, "", None).await.unwrap();
+
+        // Act / Assert - trimming is left to trim_output
+        assert_eq!(message_from_output(&output).unwrap(), "  feat: done  \n");
+    }
+
+    #[test]
+    fn test_sanitize_message_with_each_trim_policy() {
+        // Arrange - leading and trailing blank lines around an indented body
+        let raw = "\n\n  feat: keep indent\n\n";
+        let fenced = "```\n\n  feat: keep indent\n\n```";
+
+        // Act
+        let trim = sanitize_message_with(raw, TrimOutput::Trim);
+        let trim_end = sanitize_message_with(raw, TrimOutput::TrimEnd);
+        let none = sanitize_message_with(raw, TrimOutput::None);
+        let fenced_trim_end = sanitize_message_with(fenced, TrimOutput::TrimEnd);
+
+        // Assert
+        assert_eq!(trim, "feat: keep indent");
+        assert_eq!(trim_end, "\n\n  feat: keep indent");
+        assert_eq!(none, raw);
+        assert_eq!(fenced_trim_end, "\n  feat: keep indent");
+    }
+
+    #[test]
+    fn test_sanitize_message_plain_message_trimmed() {
+        // Arrange
+        let raw = "\n  feat: add login\n\n- details\n  ";
+
+        // Act
+        let message = sanitize_message(raw);
+
+        // Assert - no fence, only surrounding whitespace removed
+        assert_eq!(message, "feat: add login\n\n- details");
+    }
+
+    #[test]
+    fn test_sanitize_message_removes_fence_with_language_tag() {
+        // Arrange
+        let raw = "```text\nfeat: add login\n\n- details\n```";
+
+        // Act
+        let message = sanitize_message(raw);
+
+        // Assert
+        assert_eq!(message, "feat: add login\n\n- details");
+    }
+
+    #[test]
+    fn test_sanitize_message_removes_bare_fence() {
+        // Arrange
+        let raw = "```\nfix: handle empty diff\n```\n";
+
+        // Act
+        let message = sanitize_message(raw);
+
+        // Assert
+        assert_eq!(message, "fix: handle empty diff");
+    }
+
+    #[test]
+    fn test_sanitize_message_keeps_inline_and_inner_backticks() {
+        // Arrange - body contains inline code and its own fenced block
+        let raw = "docs: document `--json`\n\nExample:\n```bash\nclaude_commit --json\n```";
+
+        // Act
+        let message = sanitize_message(raw);
+
+        // Assert - message does not start with a fence, so nothing is removed
+        assert_eq!(message, raw);
+    }
+
+    #[test]
+    fn test_sanitize_message_unclosed_fence_untouched() {
+        // Arrange - opening fence without a closing one
+        let raw = "```text\nfeat: add login";
+
+        // Act
+        let message = sanitize_message(raw);
+
+        // Assert
+        assert_eq!(message, raw);
+    }
+
+    #[test]
+    fn test_claude_binary_from_env_over_config() {
+        // Arrange / Act
+        let program = claude_binary_from(Some("claude-wrapper".to_string()), Some("/opt/claude"));
+
+        // Assert
+        assert_eq!(program, "claude-wrapper");
+    }
+
+    #[test]
+    fn test_claude_binary_from_config() {
+        // Arrange / Act / Assert - an unset or empty env var falls through to the config
+        assert_eq!(claude_binary_from(None, Some("/opt/claude")), "/opt/claude");
+        assert_eq!(
+            claude_binary_from(Some("".to_string()), Some("/opt/claude")),
+            "/opt/claude"
+        );
+    }
+
+    #[test]
+    fn test_claude_binary_from_default() {
+        // Arrange / Act / Assert
+        assert_eq!(claude_binary_from(None, None), "claude");
+        assert_eq!(claude_binary_from(None, Some(" ")), "claude");
+    }
+
+    #[test]
+    fn test_claude_cli_backend_args_default() {
+        // Arrange
+        let backend = ClaudeCliBackend::from_config(&Config::default());
+
+        // Act
+        let args = backend.args();
+
+        // Assert - no model flag, CLI default model is used
+        assert_eq!(args, vec!["-p"]);
+    }
+
+    #[test]
+    fn test_claude_cli_backend_args_with_model() {
+        // Arrange
+        let config = Config {
+            model: Some("haiku".to_string()),
+            ..Default::default()
+        };
+        let backend = ClaudeCliBackend::from_config(&config);
+
+        // Act
+        let args = backend.args();
+
+        // Assert
+        assert_eq!(args, vec!["-p", "--model", "haiku"]);
+    }
+
+    #[test]
+    fn test_claude_cli_backend_args_with_extra_args() {
+        // Arrange
+        let config = Config {
+            model: Some("opus".to_string()),
+            extra_args: vec!["--allowedTools".to_string(), "Read".to_string()],
+            ..Default::default()
+        };
+        let backend = ClaudeCliBackend::from_config(&config);
+
+        // Act
+        let args = backend.args();
+
+        // Assert - extra args are appended verbatim, in order
+        assert_eq!(
+            args,
+            vec!["-p", "--model", "opus", "--allowedTools", "Read"]
+        );
+    }
+
+    #[test]
+    fn test_claude_cli_backend_args_with_system_prompt() {
+        // Arrange
+        let config = Config {
+            system_prompt: Some("You write commit messages.".to_string()),
+            extra_args: vec!["--verbose".to_string()],
+            ..Default::default()
+        };
+        let backend = ClaudeCliBackend::from_config(&config);
+
+        // Act
+        let args = backend.args();
+
+        // Assert - passed as its own argument, not merged into the prompt
+        assert_eq!(
+            args,
+            vec![
+                "-p",
+                "--append-system-prompt",
+                "You write commit messages.",
+                "--verbose"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prepare_prompt_excludes_system_prompt() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            system_prompt: Some("Be terse.".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let prompt = prepare_prompt("+added", &config, &PromptContext::default()).unwrap();
+
+        // Assert
+        assert_eq!(prompt, "Generate:\n\n+added");
+    }
+
+    #[test]
+    fn test_prepare_prompt_counts_system_prompt_toward_limit() {
+        // Arrange - user prompt alone fits exactly, the system prompt pushes it over
+        let user_prompt_size = "Generate:\n\n+added".len();
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: user_prompt_size,
+            ..Default::default()
+        };
+        let with_system = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: user_prompt_size,
+            system_prompt: Some("Be terse.".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let without = prepare_prompt("+added", &config, &PromptContext::default());
+        let with = prepare_prompt("+added", &with_system, &PromptContext::default());
+
+        // Assert
+        assert!(without.is_ok());
+        assert!(with.is_err());
+    }
+
+    #[test]
+    fn test_split_file_diffs() {
+        // Arrange - a preamble, a modified file and a renamed file
+        let diff = "warning: preamble\n\
+                    diff --git a/src/a.rs b/src/a.rs\n+a\n\
+                    diff --git a/old.txt b/docs/new.txt\nrename from old.txt\n";
+
+        // Act
+        let files = split_file_diffs(diff);
+
+        // Assert - the preamble is dropped, paths come from the new side
+        assert_eq!(
+            files,
+            vec![
+                FileDiff {
+                    path: "src/a.rs".to_string(),
+                    diff: "diff --git a/src/a.rs b/src/a.rs\n+a\n",
+                },
+                FileDiff {
+                    path: "docs/new.txt".to_string(),
+                    diff: "diff --git a/old.txt b/docs/new.txt\nrename from old.txt\n",
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_summarizes_large_diff() {
+        // Arrange - the diff alone exceeds the limit, so each file is summarized
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let diff = format!(
+            "diff --git a/a.rs b/a.rs\n{}diff --git a/b.rs b/b.rs\n+b\n",
+            "+a\n".repeat(100)
+        );
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 300,
+            summarize_large: true,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, &diff, &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert - the final prompt holds the summaries instead of the diff
+        let prompt = backend.prompt.lock().unwrap().clone().unwrap();
+        assert_eq!(message, "feat: from fake backend");
+        assert!(prompt.starts_with("Generate:\n\n"));
+        assert!(prompt.contains("### a.rs\nfeat: from fake backend"));
+        assert!(prompt.contains("### b.rs\nfeat: from fake backend"));
+        assert!(!prompt.contains("+a\n"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_large_diff_without_summarize_fails() {
+        // Arrange
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let diff = format!("diff --git a/a.rs b/a.rs\n{}", "+a\n".repeat(100));
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 300,
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            generate_message_with(&backend, &diff, &config, &PromptContext::default()).await;
+
+        // Assert - opt-in only: no backend call, the usual size error
+        assert!(matches!(result, Err(Error::PromptTooLarge { .. })));
+        assert!(backend.prompt.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_from_prompt_enforces_size_limit() {
+        // Arrange - the system prompt counts toward the limit
+        let config = Config {
+            max_prompt_size: 20,
+            system_prompt: Some("Be terse.".to_string()),
+            ..Default::default()
+        };
+        let prompt = "Generate:\n\n+added";
+
+        // Act - fails before the Claude CLI would be started
+        let result = generate_from_prompt(prompt, &config).await;
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(Error::PromptTooLarge {
+                actual: 17,
+                max: 11
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_generate_from_prompt_with_sends_prompt_unchanged() {
+        // Arrange
+        let backend = RecordingBackend {
+            prompt: Mutex::new(None),
+        };
+        let config = Config {
+            prompt: "ignored template".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_from_prompt_with(&backend, "Custom prompt\n\n+added", &config)
+            .await
+            .unwrap();
+
+        // Assert - the template is not applied again
+        assert_eq!(message, "feat: from fake backend");
+        assert_eq!(
+            backend.prompt.lock().unwrap().as_deref(),
+            Some("Custom prompt\n\n+added")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_retry_on_empty() {
+        // Arrange - empty output first, then a real message
+        let backend = ScriptedBackend::new(&["  \n", "feat: add login"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            retry_on_empty: true,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(message, "feat: add login");
+        assert_eq!(*backend.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_retry_on_empty_is_capped() {
+        // Arrange - always empty, with a large retry count
+        let backend = ScriptedBackend::new(&[""]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            retry_on_empty: true,
+            retries: 100,
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            generate_message_with(&backend, "+added", &config, &PromptContext::default()).await;
+
+        // Assert - the first call plus MAX_EMPTY_RETRIES, then an error
+        assert!(matches!(result, Err(Error::ClaudeFailed(e)) if e.is::<EmptyMessage>()));
+        assert_eq!(
+            *backend.calls.lock().unwrap(),
+            1 + MAX_EMPTY_RETRIES as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_rejects_empty_fenced_reply() {
+        // Arrange - non-empty stdout that sanitizes to nothing
+        let backend = ScriptedBackend::new(&["```\n\n```"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            generate_message_with(&backend, "+added", &config, &PromptContext::default()).await;
+
+        // Assert - never returned (and so never written or cached) as a message
+        assert!(matches!(result, Err(Error::ClaudeFailed(e)) if e.is::<EmptyMessage>()));
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_retry_on_empty_retries_empty_fence() {
+        // Arrange
+        let backend = ScriptedBackend::new(&["```\n```", "feat: add login"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            retry_on_empty: true,
+            ..Default::default()
+        };
+
+        // Act
+        let message = generate_message_with(&backend, "+added", &config, &PromptContext::default())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(message, "feat: add login");
+    }
+
+    #[tokio::test]
+    async fn test_generate_message_with_empty_not_retried_by_default() {
+        // Arrange
+        let backend = ScriptedBackend::new(&["", "feat: add login"]);
+        let config = Config {
+            prompt: "Generate:".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let _ = generate_message_with(&backend, "+added", &config, &PromptContext::default()).await;
+
+        // Assert
+        assert_eq!(*backend.calls.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_is_empty_message() {
+        // Arrange
+        let empty_error: anyhow::Result<String> = Err(EmptyMessage {
+            stderr: String::new(),
+        }
+        .into());
+        let other_error: anyhow::Result<String> = Err(anyhow::anyhow!("exit code 1"));
+
+        // Act / Assert - only empty output counts, not other failures
+        assert!(is_empty_message(&empty_error));
+        assert!(is_empty_message(&Ok(" \n".to_string())));
+        assert!(!is_empty_message(&other_error));
+        assert!(!is_empty_message(&Ok("feat: x".to_string())));
+    }
+
+    #[test]
+    fn test_cache_misses_when_model_changes() {
+        // Arrange - a message cached for one model
+        let sonnet = Config {
+            model: Some("sonnet".to_string()),
+            ..Default::default()
+        };
+        let opus = Config {
+            model: Some("opus".to_string()),
+            ..sonnet.clone()
+        };
+        let prompt = "Generate:\n\n+added";
+        let mut cache = cache::MessageCache::default();
+        cache.insert(
+            cache::prompt_key(&cache_input(&sonnet, prompt)),
+            "feat: cached",
+            100,
+        );
+
+        // Act
+        let same = cache.get(&cache::prompt_key(&cache_input(&sonnet, prompt)), 60, 100);
+        let other = cache.get(&cache::prompt_key(&cache_input(&opus, prompt)), 60, 100);
+
+        // Assert - the same prompt with another model is a miss
+        assert_eq!(same, Some("feat: cached"));
+        assert_eq!(other, None);
+    }
+
+    #[test]
+    fn test_cache_input_covers_generation_settings() {
+        // Arrange
+        let base = Config::default();
+        let variants = [
+            Config {
+                system_prompt: Some("Be terse.".to_string()),
+                ..base.clone()
+            },
+            Config {
+                extra_args: vec!["--verbose".to_string()],
+                ..base.clone()
+            },
+            Config {
+                claude_binary: Some("/opt/claude".to_string()),
+                ..base.clone()
+            },
+            Config {
+                trim_output: TrimOutput::None,
+                ..base.clone()
+            },
+        ];
+
+        // Act / Assert - every setting changes the key
+        for variant in &variants {
+            assert_ne!(cache_input(variant, "p"), cache_input(&base, "p"));
+        }
+    }
 }
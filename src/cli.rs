@@ -1,16 +1,31 @@
 //! CLI argument definitions and subcommand implementations
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 
-use crate::config::DEFAULT_CONFIG_CONTENT;
-use crate::git::get_git_root;
+use crate::color;
+use crate::config::{Config, DEFAULT_CONFIG_CONTENT, load_configs};
+use crate::git::{DiffMode, generated_message_path, get_hooks_dir, validate_range};
+use crate::log;
+use crate::log::LogFormat;
+use crate::output::OutputFormat;
+use crate::prompt::SEPARATOR;
+use crate::trailer::parse_trailer;
 
 /// Command-line arguments
 #[derive(Parser)]
 #[command(name = "claude_commit")]
 #[command(about = "Generate git commit messages using Claude AI", long_about = None)]
+#[command(group(ArgGroup::new("json_mode").args(["json", "json_pretty", "output_format"])))]
+#[command(group(
+    ArgGroup::new("no_commit")
+        .args([
+            "json", "json_pretty", "output_format", "dry_run", "print_size", "write_message",
+            "clipboard",
+        ])
+        .multiple(true)
+))]
 pub struct Args {
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -19,10 +34,320 @@ pub struct Args {
     #[arg(long)]
     pub json: bool,
 
-    /// Path to the prompt configuration file (TOML format).
-    /// If omitted, searches: ~/.config/claude_commit/config.toml → <git root>/.claude_commit.toml → ./.claude_commit.toml
+    /// Output in pretty-printed JSON format (implies JSON mode)
+    #[arg(long, conflicts_with = "json")]
+    pub json_pretty: bool,
+
+    /// Output in the given machine-readable format (`toml` for TOML-driven tools);
+    /// git commit will not be executed
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub output_format: Option<OutputFormat>,
+
+    /// Write the machine-readable output (JSON or TOML) to a file instead of stdout
+    /// (requires --json, --json-pretty or --output-format). Parent directories are
+    /// created as needed
+    #[arg(long, value_name = "PATH", requires = "json_mode")]
+    pub output: Option<String>,
+
+    /// Path to the prompt configuration file (TOML, or JSON/YAML by extension).
+    /// Can be repeated to layer files, later ones overriding earlier ones.
+    /// If omitted, searches: ./prompt.toml → ./.claude_commit.toml → <git root>/.claude_commit.toml
+    /// → $XDG_CONFIG_HOME/claude_commit/config.toml → ~/.config/claude_commit/config.toml
+    #[arg(long)]
+    pub config: Vec<String>,
+
+    /// Use the prompt of the named `[profiles.<name>]` table in the config file
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Which changes to generate the message from.
+    /// Note that only staged changes are committed regardless of this setting.
+    #[arg(long, value_enum, default_value_t = DiffMode::Staged)]
+    pub diff_mode: DiffMode,
+
+    /// Generate the message from the changes in a commit range (e.g. `main..HEAD`)
+    /// instead of the staged changes. Nothing is committed, so one of --json,
+    /// --dry-run, --print-size, --write-message or --clipboard is required
+    #[arg(
+        long,
+        value_name = "RANGE",
+        value_parser = parse_range_arg,
+        requires = "no_commit",
+        conflicts_with_all = [
+            "diff_mode", "amend", "stage_all", "diff_file", "diff_stdin", "no_edit", "only",
+            "write_editmsg"
+        ]
+    )]
+    pub range: Option<String>,
+
+    /// Describe the staged changes together with everything committed since a
+    /// revision or date (e.g. `main`, `yesterday`), diffing the index against it
+    #[arg(
+        long,
+        value_name = "REV_OR_DATE",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        conflicts_with_all = ["diff_mode", "amend", "range", "diff_file", "diff_stdin"]
+    )]
+    pub since: Option<String>,
+
+    /// Restrict the diff to the given path (can be repeated)
+    #[arg(long = "path", value_name = "PATH")]
+    pub paths: Vec<String>,
+
+    /// Generate the message from the staged diff of this one file only
+    /// (`git diff --cached -- <PATH>`); the commit itself is unchanged
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "paths", "only", "diff_mode", "range", "since", "amend", "diff_file", "diff_stdin"
+        ]
+    )]
+    pub file: Option<String>,
+
+    /// Generate the message from and commit only the given path (can be repeated).
+    /// Other staged changes stay staged
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["paths", "diff_file", "diff_stdin", "write_message"]
+    )]
+    pub only: Vec<String>,
+
+    /// Claude model to use (overrides `model` in the config file)
     #[arg(long)]
-    pub config: Option<String>,
+    pub model: Option<String>,
+
+    /// Language to write the message in, e.g. `ja` (overrides `language` in the config file)
+    #[arg(long, value_name = "LANGUAGE")]
+    pub lang: Option<String>,
+
+    /// Number of candidate messages to generate (overrides `candidates` in the config file)
+    #[arg(long, value_name = "N")]
+    pub candidates: Option<usize>,
+
+    /// Maximum prompt size in bytes for this run (overrides `max_prompt_size` in the config file)
+    #[arg(long, value_name = "BYTES", value_parser = parse_max_prompt_size_arg)]
+    pub max_prompt_size: Option<usize>,
+
+    /// Commit directly with the generated message, without prompting or opening an editor
+    #[arg(long, conflicts_with_all = ["json", "json_pretty", "output_format"])]
+    pub no_edit: bool,
+
+    /// Regenerate the message of the last commit and amend it (rewrites history).
+    /// Staged changes, if any, are folded into the amended commit
+    #[arg(long)]
+    pub amend: bool,
+
+    /// Fold staged changes into the last commit and keep its message
+    /// (`git commit --amend --no-edit`) without calling Claude
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "amend", "json", "json_pretty", "no_edit", "write_message", "write_editmsg",
+            "dry_run", "print_size", "clipboard", "candidates", "stream",
+            "range", "since", "paths", "diff_file", "diff_stdin"
+        ]
+    )]
+    pub amend_keep_message: bool,
+
+    /// Append a git trailer such as `Refs=#123` to the message (can be repeated).
+    /// Added after the `trailers` from the config file
+    #[arg(long = "trailer", value_name = "KEY=VALUE", value_parser = parse_trailer_arg)]
+    pub trailers: Vec<String>,
+
+    /// GPG-sign the commit (`git commit -S`); requires git's signing key to be configured
+    #[arg(long)]
+    pub sign: bool,
+
+    /// Skip the pre-commit and commit-msg hooks (`git commit --no-verify`).
+    /// The pre-commit hook is not run before generation either
+    #[arg(long, conflicts_with = "write_message")]
+    pub no_verify: bool,
+
+    /// Also write the generated message to `.git/COMMIT_EDITMSG` before committing
+    #[arg(long)]
+    pub write_editmsg: bool,
+
+    /// Stage all changes (`git add -A`) before generating the message
+    ///
+    /// Not allowed with `--dry-run`, which must leave the index untouched.
+    #[arg(
+        short = 'a',
+        long,
+        conflicts_with_all = ["diff_file", "diff_stdin", "dry_run"]
+    )]
+    pub stage_all: bool,
+
+    /// Read the diff from a file instead of running `git diff`
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["diff_stdin", "diff_mode", "paths", "amend"]
+    )]
+    pub diff_file: Option<String>,
+
+    /// Read the diff from standard input instead of running `git diff`
+    #[arg(long, conflicts_with_all = ["diff_mode", "paths", "amend"])]
+    pub diff_stdin: bool,
+
+    /// Print the prompt that would be sent to Claude and exit without calling it
+    #[arg(long, conflicts_with_all = ["no_edit", "amend"])]
+    pub dry_run: bool,
+
+    /// Print the size of the prompt and its share of `max_prompt_size`, then exit
+    /// without calling Claude or running the pre-commit hook
+    #[arg(long, conflicts_with_all = ["dry_run", "no_edit", "write_message"])]
+    pub print_size: bool,
+
+    /// Print the JSON Schema of the `--json` / `--output-format` output and exit.
+    /// The TOML output has the same structure
+    #[arg(long)]
+    pub print_schema: bool,
+
+    /// Show Claude's output as it is generated instead of a spinner
+    #[arg(long, conflicts_with = "json_mode")]
+    pub stream: bool,
+
+    /// Always call Claude instead of reusing a cached message for an unchanged diff
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Only print warnings, errors and the requested output
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also print details such as the config path, diff size and model
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Format of status lines on stderr (`json` writes one JSON object per line)
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Disable colored status messages (also disabled by the NO_COLOR environment variable)
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Write the generated message to a file instead of committing
+    /// (used by the prepare-commit-msg hook)
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["json", "json_pretty", "output_format", "no_edit", "amend"]
+    )]
+    pub write_message: Option<String>,
+
+    /// Copy the generated message to the system clipboard; on its own, copy
+    /// instead of committing (requires the `clipboard` feature)
+    #[arg(long, conflicts_with_all = ["dry_run", "print_size"])]
+    pub clipboard: bool,
+
+    /// Generate a message even if added lines contain merge-conflict markers
+    #[arg(long)]
+    pub allow_conflict_markers: bool,
+
+    /// Generate a message even while a merge or rebase is in progress
+    #[arg(long)]
+    pub force: bool,
+
+    /// Include the message in `.git/COMMIT_EDITMSG` for Claude to improve on
+    /// (enables `reuse_editmsg`)
+    #[arg(long)]
+    pub reuse_editmsg: bool,
+}
+
+impl Args {
+    /// Machine-readable output format, or `None` for interactive mode
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        if let Some(format) = self.output_format {
+            Some(format)
+        } else if self.json_pretty {
+            Some(OutputFormat::JsonPretty)
+        } else if self.json {
+            Some(OutputFormat::Json)
+        } else {
+            None
+        }
+    }
+
+    /// Reject flags that `regenerate` would otherwise silently ignore
+    ///
+    /// Regenerate only rewrites the message file, so output, commit and
+    /// candidate flags have no effect there.
+    ///
+    /// # Errors
+    ///
+    /// * The subcommand is `regenerate` and such a flag is given
+    pub fn check_regenerate_flags(&self) -> Result<()> {
+        if !matches!(self.command, Some(Commands::Regenerate)) {
+            return Ok(());
+        }
+        let ignored: Vec<&str> = [
+            (self.json, "--json"),
+            (self.json_pretty, "--json-pretty"),
+            (self.output_format.is_some(), "--output-format"),
+            (self.output.is_some(), "--output"),
+            (self.clipboard, "--clipboard"),
+            (self.no_edit, "--no-edit"),
+            (self.write_message.is_some(), "--write-message"),
+            (self.write_editmsg, "--write-editmsg"),
+            (self.candidates.is_some(), "--candidates"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect();
+        if !ignored.is_empty() {
+            anyhow::bail!(
+                "regenerate only rewrites the message file and cannot be used with {}",
+                ignored.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Apply the flags that take precedence over config file values
+    pub fn apply_config_overrides(&self, config: &mut Config) {
+        if let Some(model) = &self.model {
+            config.model = Some(model.clone());
+        }
+        if let Some(language) = &self.lang {
+            config.language = Some(language.clone());
+        }
+        if let Some(candidates) = self.candidates {
+            config.candidates = candidates;
+        }
+        if let Some(max_prompt_size) = self.max_prompt_size {
+            config.max_prompt_size = max_prompt_size;
+        }
+        config.trailers.extend(self.trailers.iter().cloned());
+        config.reuse_editmsg |= self.reuse_editmsg;
+    }
+}
+
+/// Validate a `--max-prompt-size` value with the same bound as the config field
+fn parse_max_prompt_size_arg(value: &str) -> std::result::Result<usize, String> {
+    let size: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if size <= SEPARATOR.len() {
+        return Err(format!(
+            "must be greater than {} bytes (got {})",
+            SEPARATOR.len(),
+            size
+        ));
+    }
+    Ok(size)
+}
+
+/// Validate a `--range` value so that git never reads it as an option
+fn parse_range_arg(value: &str) -> std::result::Result<String, String> {
+    validate_range(value)
+        .map(|()| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Validate a `--trailer` value, returning it in `Key: value` form
+fn parse_trailer_arg(value: &str) -> std::result::Result<String, String> {
+    parse_trailer(value).ok_or_else(|| format!("expected KEY=VALUE, got '{}'", value))
 }
 
 #[derive(Subcommand)]
@@ -37,6 +362,20 @@ pub enum Commands {
         #[arg(long)]
         force: bool,
     },
+    /// Install a prepare-commit-msg hook that fills in the message on `git commit`
+    InstallHook {
+        /// Overwrite an existing prepare-commit-msg hook
+        #[arg(long)]
+        force: bool,
+    },
+    /// Validate the config file(s) and exit without touching the repository
+    CheckConfig,
+    /// Generate a fresh message into the message file
+    /// (`<git dir>/COMMIT_MSG_GENERATED`) and show how it changed.
+    /// Uses the same diff as a normal run (--path, --file, --since, ...),
+    /// skips the message cache and the pre-commit hook, and never commits.
+    /// Output and commit flags (--json, --no-edit, --candidates, ...) are rejected
+    Regenerate,
 }
 
 /// Create a default configuration file at the specified path
@@ -59,8 +398,10 @@ pub fn run_init(output_path: Option<&str>, force: bool) -> Result<()> {
     };
 
     if path.exists() && !force {
-        eprintln!("Error: '{}' already exists.", path.display());
-        eprintln!("Use --force to overwrite.");
+        log::error(format!(
+            "'{}' already exists.\nUse --force to overwrite.",
+            path.display()
+        ));
         std::process::exit(1);
     }
 
@@ -73,42 +414,494 @@ pub fn run_init(output_path: Option<&str>, force: bool) -> Result<()> {
     std::fs::write(&path, DEFAULT_CONFIG_CONTENT)
         .map_err(|e| anyhow::anyhow!("Failed to write config file '{}': {}", path.display(), e))?;
 
-    println!("Created config file: {}", path.display());
+    println!(
+        "{}",
+        color::success(&format!("Created config file: {}", path.display()))
+    );
     println!("Edit the 'prompt' field to customize the commit message style.");
     Ok(())
 }
 
-/// Find a config file by searching in standard locations
+/// Load and validate config files the same way a normal run does
+///
+/// Runs every load-time check: parsing, `prompt` / `prompt_file` (including
+/// that the prompt file can be read), the empty-prompt check and the field
+/// validations. Returns `Config OK: <paths>` for the caller to print.
+///
+/// # Errors
+///
+/// * Any check fails; the error names the config and the specific problem
+pub fn check_config(config_paths: &[String], profile: Option<&str>) -> Result<String> {
+    let sources = if config_paths.is_empty() {
+        "(environment variables)".to_string()
+    } else {
+        config_paths.join(", ")
+    };
+    load_configs(config_paths, profile).with_context(|| format!("Invalid config: {}", sources))?;
+    Ok(format!("Config OK: {}", sources))
+}
+
+/// Install a `prepare-commit-msg` hook that runs claude_commit with `config_path`
 ///
-/// Search order:
-/// 1. `~/.config/claude_commit/config.toml` (recommended)
-/// 2. `<git root>/.claude_commit.toml`
-/// 3. `./.claude_commit.toml`
-pub fn find_config_file() -> Option<PathBuf> {
-    // 1. ~/.config/claude_commit/config.toml (recommended)
-    if let Ok(home) = std::env::var("HOME") {
-        let home_config = PathBuf::from(home)
-            .join(".config")
-            .join("claude_commit")
-            .join("config.toml");
-        if home_config.exists() {
-            return Some(home_config);
+/// The hook is written to the directory returned by [`get_hooks_dir`], so it
+/// also applies to worktrees. Refuses to overwrite an existing hook unless
+/// `force` is true.
+pub fn run_install_hook(config_path: &Path, force: bool) -> Result<()> {
+    let config_path = std::fs::canonicalize(config_path)
+        .with_context(|| format!("Config file not found: {}", config_path.display()))?;
+    let hooks_dir = get_hooks_dir()?;
+    let path = hooks_dir.join("prepare-commit-msg");
+
+    if path.exists() && !force {
+        log::error(format!(
+            "'{}' already exists.\nUse --force to overwrite.",
+            path.display()
+        ));
+        std::process::exit(1);
+    }
+
+    std::fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Failed to create directory '{}'", hooks_dir.display()))?;
+    std::fs::write(&path, hook_script(&config_path.to_string_lossy()))
+        .with_context(|| format!("Failed to write hook '{}'", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make hook executable '{}'", path.display()))?;
+    }
+
+    println!(
+        "{}",
+        color::success(&format!("Installed hook: {}", path.display()))
+    );
+    Ok(())
+}
+
+/// Store a regenerated message in the message file and print what changed
+///
+/// The file is `message_file`, or `<git dir>/COMMIT_MSG_GENERATED`. The
+/// line diff against the previous message (or the whole message, when there
+/// was none) is the command's output on stdout; status lines go through
+/// [`log`].
+pub fn write_regenerated(message: &str, message_file: Option<&str>) -> Result<()> {
+    let path = generated_message_path(message_file)?;
+    match replace_message_file(&path, message)? {
+        None => println!("{}", message),
+        Some(previous) if previous == message => {
+            log::info("The new message is the same as before.");
+        }
+        Some(previous) => {
+            for line in message_diff(&previous, message) {
+                match line.chars().next() {
+                    Some('-') => println!("{}", color::error(&line)),
+                    Some('+') => println!("{}", color::success(&line)),
+                    _ => println!("{}", line),
+                }
+            }
+        }
+    }
+    log::info(color::success(&format!("Wrote {}", path.display())));
+    Ok(())
+}
+
+/// Overwrite `path` with `message`, returning its previous content
+///
+/// Returns `None` when the file did not exist yet.
+pub fn replace_message_file(path: &Path, message: &str) -> Result<Option<String>> {
+    let previous = match std::fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    std::fs::write(path, message)
+        .with_context(|| format!("Failed to write commit message to {}", path.display()))?;
+    Ok(previous)
+}
+
+/// Line diff from `old` to `new`, each line prefixed with `- `, `+ ` or two spaces
+///
+/// Lines are matched by longest common subsequence; within a change,
+/// removed lines come before added ones.
+fn message_diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
         }
     }
 
-    // 2. Git repository root
-    if let Ok(root) = get_git_root() {
-        let git_root_config = root.join(".claude_commit.toml");
-        if git_root_config.exists() {
-            return Some(git_root_config);
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
         }
     }
+    lines
+}
+
+/// Build the `prepare-commit-msg` hook script
+///
+/// The hook only runs for a plain `git commit`; when git already has a message
+/// source (`-m`, `-F`, merge, squash, amend) it leaves the message alone.
+/// Generation failures never block the commit.
+fn hook_script(config_path: &str) -> String {
+    format!(
+        r#"#!/bin/sh
+# Installed by `claude_commit install-hook`
+if [ -n "$2" ]; then
+    exit 0
+fi
+claude_commit --config {} --write-message "$1" ||
+    echo "claude_commit: failed to generate a commit message" >&2
+exit 0
+"#,
+        shell_quote(config_path)
+    )
+}
+
+/// Quote a string for POSIX sh using single quotes
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_hook_script_contains_config_path() {
+        // Arrange / Act
+        let script = hook_script("/home/user/.config/claude_commit/config.toml");
+
+        // Assert
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("--config '/home/user/.config/claude_commit/config.toml'"));
+        assert!(script.contains(r#"--write-message "$1""#));
+    }
+
+    #[test]
+    fn test_replace_message_file_overwrites_and_returns_previous() {
+        // Arrange
+        let dir = TempDir::new("regenerate");
+        let path = dir.join("COMMIT_MSG_GENERATED");
+
+        // Act
+        let first = replace_message_file(&path, "feat: first take").unwrap();
+        let second = replace_message_file(&path, "feat: second take").unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        // Assert - the file holds the new message; the old one is returned
+        assert_eq!(first, None);
+        assert_eq!(second.as_deref(), Some("feat: first take"));
+        assert_eq!(content, "feat: second take");
+    }
+
+    #[test]
+    fn test_message_diff_marks_changed_lines() {
+        // Arrange
+        let old = "feat: add login\n\n- OAuth support\n- Tests";
+        let new = "feat(auth): add login\n\n- OAuth support\n- Session refresh\n- Tests";
+
+        // Act
+        let diff = message_diff(old, new);
+
+        // Assert
+        assert_eq!(
+            diff,
+            vec![
+                "- feat: add login",
+                "+ feat(auth): add login",
+                "  ",
+                "  - OAuth support",
+                "+ - Session refresh",
+                "  - Tests",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shell_quote_escapes_single_quotes() {
+        // Arrange / Act / Assert
+        assert_eq!(shell_quote("/tmp/it's.toml"), r"'/tmp/it'\''s.toml'");
+    }
+
+    #[test]
+    fn test_max_prompt_size_flag_overrides_config() {
+        // Arrange
+        let args = Args::try_parse_from(["claude_commit", "--max-prompt-size", "20"]).unwrap();
+        let mut config = Config {
+            prompt: "Generate:".to_string(),
+            max_prompt_size: 1_000_000,
+            ..Default::default()
+        };
+
+        // Act
+        args.apply_config_overrides(&mut config);
+        let result = crate::claude::prepare_prompt(
+            "+a diff longer than twenty bytes",
+            &config,
+            &Default::default(),
+        );
+
+        // Assert - the CLI limit is the one enforced
+        assert_eq!(config.max_prompt_size, 20);
+        assert!(matches!(
+            result,
+            Err(crate::Error::PromptTooLarge { max: 20, .. })
+        ));
+    }
+
+    #[test]
+    fn test_max_prompt_size_flag_absent_keeps_config() {
+        // Arrange
+        let args = Args::try_parse_from(["claude_commit"]).unwrap();
+        let mut config = Config {
+            max_prompt_size: 500,
+            ..Default::default()
+        };
+
+        // Act
+        args.apply_config_overrides(&mut config);
+
+        // Assert
+        assert_eq!(config.max_prompt_size, 500);
+    }
+
+    #[test]
+    fn test_max_prompt_size_flag_rejects_zero() {
+        // Arrange / Act
+        let result = Args::try_parse_from(["claude_commit", "--max-prompt-size", "0"]);
+
+        // Assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_max_prompt_size_flag_rejects_sizes_below_separator() {
+        // Arrange / Act - the same bound validate_config applies to the config value
+        let too_small = Args::try_parse_from(["claude_commit", "--max-prompt-size", "2"]);
+        let smallest = Args::try_parse_from(["claude_commit", "--max-prompt-size", "3"]);
+
+        // Assert
+        assert!(too_small.is_err());
+        assert_eq!(smallest.unwrap().max_prompt_size, Some(3));
+    }
+
+    #[test]
+    fn test_trailer_flags_merge_after_config() {
+        // Arrange
+        let args = Args::try_parse_from([
+            "claude_commit",
+            "--trailer",
+            "Refs=#123",
+            "--trailer",
+            "Reviewed-by: Bob",
+        ])
+        .unwrap();
+        let mut config = Config {
+            trailers: vec!["Signed-off-by: Alice".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        args.apply_config_overrides(&mut config);
+
+        // Assert - normalized to `Key: value`, config entries first
+        assert_eq!(
+            config.trailers,
+            vec!["Signed-off-by: Alice", "Refs: #123", "Reviewed-by: Bob"]
+        );
+    }
+
+    #[test]
+    fn test_trailer_flag_rejects_missing_separator() {
+        // Arrange / Act
+        let result = Args::try_parse_from(["claude_commit", "--trailer", "Refs"]);
+
+        // Assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_range_flag_requires_a_non_committing_mode() {
+        // Arrange / Act
+        let committing = Args::try_parse_from(["claude_commit", "--range", "main..HEAD"]);
+        let no_edit = Args::try_parse_from(["claude_commit", "--range", "main..HEAD", "--no-edit"]);
+        let json = Args::try_parse_from(["claude_commit", "--range", "main..HEAD", "--json"]);
+
+        // Assert - a range is only ever described, never committed
+        assert!(committing.is_err());
+        assert!(no_edit.is_err());
+        assert_eq!(json.unwrap().range.as_deref(), Some("main..HEAD"));
+    }
+
+    #[test]
+    fn test_range_flag_rejects_leading_dash() {
+        // Arrange / Act
+        let result = Args::try_parse_from(["claude_commit", "--dry-run", "--range=--output=x"]);
+
+        // Assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stage_all_conflicts_with_dry_run() {
+        // Arrange / Act
+        let result = Args::try_parse_from(["claude_commit", "--stage-all", "--dry-run"]);
+
+        // Assert - a dry run never runs `git add -A`
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_regenerate_flags_rejects_ignored_flags() {
+        // Arrange
+        let plain = Args::try_parse_from(["claude_commit", "--path", "src", "regenerate"]).unwrap();
+        let no_edit = Args::try_parse_from(["claude_commit", "--no-edit", "regenerate"]).unwrap();
+        let json = Args::try_parse_from([
+            "claude_commit",
+            "--json",
+            "--output",
+            "out.json",
+            "regenerate",
+        ])
+        .unwrap();
+
+        // Act / Assert - every dropped flag is named
+        assert!(plain.check_regenerate_flags().is_ok());
+        assert!(
+            no_edit
+                .check_regenerate_flags()
+                .unwrap_err()
+                .to_string()
+                .contains("--no-edit")
+        );
+        let message = json.check_regenerate_flags().unwrap_err().to_string();
+        assert!(message.contains("--json, --output"));
+    }
+
+    #[test]
+    fn test_check_regenerate_flags_ignores_other_runs() {
+        // Arrange / Act
+        let args = Args::try_parse_from(["claude_commit", "--no-edit"]).unwrap();
+
+        // Assert
+        assert!(args.check_regenerate_flags().is_ok());
+    }
+
+    #[test]
+    fn test_output_flag_requires_json() {
+        // Arrange / Act
+        let without_json = Args::try_parse_from(["claude_commit", "--output", "out.json"]);
+        let with_json =
+            Args::try_parse_from(["claude_commit", "--json-pretty", "--output", "out.json"]);
+
+        // Assert
+        assert!(without_json.is_err());
+        assert_eq!(with_json.unwrap().output.as_deref(), Some("out.json"));
+    }
+
+    #[test]
+    fn test_amend_keep_message_rejects_generation_flags() {
+        // Arrange / Act - flags that only matter when a message is generated
+        let plain = Args::try_parse_from(["claude_commit", "--amend-keep-message"]);
+        let hooks = Args::try_parse_from([
+            "claude_commit",
+            "--amend-keep-message",
+            "--sign",
+            "--no-verify",
+            "-a",
+        ]);
+        let rejected = ["--json", "--dry-run", "--no-edit", "--amend", "--clipboard"]
+            .map(|flag| Args::try_parse_from(["claude_commit", "--amend-keep-message", flag]));
+
+        // Assert
+        assert!(plain.unwrap().amend_keep_message);
+        assert!(hooks.is_ok());
+        assert!(rejected.iter().all(|result| result.is_err()));
+    }
+
+    #[test]
+    fn test_output_format_flag() {
+        // Arrange / Act
+        let toml = Args::try_parse_from(["claude_commit", "--output-format", "toml"]).unwrap();
+        let pretty =
+            Args::try_parse_from(["claude_commit", "--output-format", "json-pretty"]).unwrap();
+        let both = Args::try_parse_from(["claude_commit", "--json", "--output-format", "toml"]);
+
+        // Assert
+        assert_eq!(toml.output_format(), Some(OutputFormat::Toml));
+        assert_eq!(pretty.output_format(), Some(OutputFormat::JsonPretty));
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn test_run_init_writes_loadable_config() {
+        // Arrange
+        let dir = TempDir::new("init");
+        let path = dir.join("prompt.toml");
 
-    // 3. Current directory
-    let local = PathBuf::from(".claude_commit.toml");
-    if local.exists() {
-        return Some(local);
+        // Act
+        run_init(Some(path.to_str().unwrap()), false).unwrap();
+        let config = crate::config::load_config(path.to_str().unwrap());
+
+        // Assert - the scaffold is a valid config with the default size limit
+        let config = config.unwrap();
+        assert!(!config.prompt.trim().is_empty());
+        assert_eq!(config.max_prompt_size, 1_000_000);
     }
 
-    None
+    #[test]
+    fn test_check_config_valid() {
+        // Arrange
+        let dir = TempDir::new("check_ok");
+        let path = dir.join("prompt.toml");
+        std::fs::write(&path, r#"prompt = "Generate:""#).unwrap();
+        let paths = [path.to_string_lossy().to_string()];
+
+        // Act
+        let result = check_config(&paths, None);
+
+        // Assert
+        assert_eq!(result.unwrap(), format!("Config OK: {}", paths[0]));
+    }
+
+    #[test]
+    fn test_check_config_malformed() {
+        // Arrange
+        let dir = TempDir::new("check_bad");
+        let path = dir.join("prompt.toml");
+        std::fs::write(&path, "prompt = \"unclosed\nmax_prompt_size = 0\n").unwrap();
+        let paths = [path.to_string_lossy().to_string()];
+
+        // Act
+        let result = check_config(&paths, None);
+
+        // Assert - an error (non-zero exit in main) naming the file and the problem
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains(&format!("Invalid config: {}", paths[0])));
+        assert!(message.contains("Failed to parse config file as TOML"));
+    }
 }
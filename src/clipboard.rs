@@ -0,0 +1,75 @@
+//! Copying generated messages to the system clipboard (`--clipboard`)
+//!
+//! Clipboard access needs the optional `clipboard` feature so that the default
+//! build stays free of the platform dependencies it pulls in:
+//!
+//! ```sh
+//! cargo install --path . --features clipboard
+//! ```
+//!
+//! Without the feature, [`copy_to_clipboard`] fails with
+//! [`Error::ClipboardUnavailable`].
+
+use crate::error::{Error, Result};
+
+/// Whether this build can access the clipboard
+pub const AVAILABLE: bool = cfg!(feature = "clipboard");
+
+/// Fail early when `--clipboard` is requested in a build without the feature
+///
+/// # Errors
+///
+/// * The `clipboard` feature is disabled
+pub fn ensure_available() -> Result<()> {
+    if AVAILABLE {
+        Ok(())
+    } else {
+        Err(Error::ClipboardUnavailable)
+    }
+}
+
+/// Copy `text` to the system clipboard
+///
+/// # Errors
+///
+/// * The `clipboard` feature is disabled
+/// * The clipboard cannot be opened or written (e.g. no display server)
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| Error::ClipboardFailed(e.to_string()))
+}
+
+/// Copy `text` to the system clipboard
+///
+/// # Errors
+///
+/// * The `clipboard` feature is disabled
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_text: &str) -> Result<()> {
+    Err(Error::ClipboardUnavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_available_follows_feature() {
+        // Act / Assert
+        assert_eq!(ensure_available().is_ok(), AVAILABLE);
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    #[test]
+    fn test_copy_without_feature_errors_clearly() {
+        // Act
+        let result = copy_to_clipboard("feat: add login");
+
+        // Assert
+        let error = result.unwrap_err();
+        assert!(matches!(error, Error::ClipboardUnavailable));
+        assert!(error.to_string().contains("--features clipboard"));
+    }
+}
@@ -0,0 +1,97 @@
+//! ANSI coloring for human-facing status messages
+//!
+//! Coloring is off by default so library users never get escape codes in
+//! their output; the binary turns it on with [`set_enabled`] when stdout is a
+//! terminal. Generated commit messages and JSON output are never colored.
+
+use std::ffi::OsStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Environment variable that disables color when set to a non-empty value
+///
+/// See <https://no-color.org>.
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn coloring of status messages on or off
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether status messages are currently colored
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Decide whether to color status messages
+///
+/// Color is used only when stdout is a terminal, `--no-color` is not given,
+/// output is not machine-readable, and `NO_COLOR` is unset or empty.
+pub fn should_enable(
+    no_color_flag: bool,
+    machine_output: bool,
+    no_color_env: Option<&OsStr>,
+    stdout_is_tty: bool,
+) -> bool {
+    let no_color_env = no_color_env.is_some_and(|value| !value.is_empty());
+    stdout_is_tty && !no_color_flag && !machine_output && !no_color_env
+}
+
+/// Green text for successful steps
+pub fn success(text: &str) -> String {
+    paint("32", text)
+}
+
+/// Yellow text for warnings
+pub fn warning(text: &str) -> String {
+    paint("33", text)
+}
+
+/// Red text for errors
+pub fn error(text: &str) -> String {
+    paint("31", text)
+}
+
+/// Wrap `text` in the SGR color `code` when coloring is enabled
+fn paint(code: &str, text: &str) -> String {
+    if enabled() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_enable_on_tty_by_default() {
+        // Arrange / Act / Assert
+        assert!(should_enable(false, false, None, true));
+    }
+
+    #[test]
+    fn test_should_enable_respects_opt_outs() {
+        // Arrange / Act / Assert - each condition alone disables color
+        assert!(!should_enable(false, false, None, false));
+        assert!(!should_enable(true, false, None, true));
+        assert!(!should_enable(false, true, None, true));
+        assert!(!should_enable(false, false, Some(OsStr::new("1")), true));
+    }
+
+    #[test]
+    fn test_should_enable_ignores_empty_no_color() {
+        // Arrange / Act / Assert
+        assert!(should_enable(false, false, Some(OsStr::new("")), true));
+    }
+
+    #[test]
+    fn test_paint_is_plain_when_disabled() {
+        // Arrange - coloring is off unless the binary enables it
+        // Act / Assert
+        assert_eq!(success("done"), "done");
+        assert_eq!(warning("Warning:"), "Warning:");
+    }
+}
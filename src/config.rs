@@ -1,11 +1,21 @@
 //! Configuration management for Claude Commit
 //!
-//! This module handles loading and parsing configuration files in TOML format.
+//! This module handles loading and parsing configuration files in TOML format
+//! (JSON and YAML are also accepted, selected by file extension).
 //! The configuration contains the prompt template to be sent to Claude AI.
 
-use anyhow::{Context, Result};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::DEFAULT_CACHE_TTL_SECS;
+use crate::error::{Error, Result};
+use crate::git::get_git_root;
+use crate::log;
+use crate::prompt::SEPARATOR;
+use crate::redact::Redactor;
+use crate::trailer::parse_trailer;
 
 /// Prompt configuration file structure
 ///
@@ -19,15 +29,328 @@ use std::fs;
 ///
 /// # Optional: Maximum combined size of prompt + diff in bytes (default: 1,000,000)
 /// max_prompt_size = 1000000
+///
+/// # Optional: Paths to leave out of the diff (git pathspec patterns)
+/// exclude_paths = ["Cargo.lock", "*.min.js"]
+///
+/// # Optional: Summarize binary files instead of sending their diff (default: false)
+/// strip_binary = true
 /// ```
-#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct Config {
     /// Prompt template to send to Claude
+    ///
+    /// Exactly one of `prompt`, `prompt_parts` and `prompt_file` must be set;
+    /// after loading, this holds the effective prompt from whichever is used.
+    #[serde(default)]
     pub prompt: String,
+    /// Prompt snippets joined with newlines, in order, to form the prompt
+    #[serde(default)]
+    pub prompt_parts: Vec<String>,
+    /// File to read the prompt template from, relative to the config file's directory
+    #[serde(default)]
+    pub prompt_file: Option<String>,
     /// Maximum combined size of prompt template and git diff in bytes
     /// Defaults to 1MB (1,000,000 bytes)
     #[serde(default = "default_max_prompt_size")]
     pub max_prompt_size: usize,
+    /// Fraction of `max_prompt_size` above which a warning is printed (0.0-1.0)
+    ///
+    /// Defaults to 0.8; `1.0` disables the warning.
+    #[serde(default = "default_warn_threshold")]
+    pub warn_threshold: f32,
+    /// Paths excluded from the diff before it is sent to Claude
+    ///
+    /// Each entry is passed to git as an `:(exclude)` pathspec, so glob
+    /// patterns follow git's pathspec rules (e.g. `*.lock` matches at any
+    /// depth, `:(glob)` magic is not implied).
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+    /// Replace binary file diffs with a one-line summary
+    #[serde(default)]
+    pub strip_binary: bool,
+    /// Label newly added files with a `[NEW FILE]` line after their diff header
+    #[serde(default)]
+    pub mark_new_files: bool,
+    /// Drop intent-to-add files (`git add -N`), which show up in the staged
+    /// diff as a new-file header without content
+    ///
+    /// Staged empty files (`__init__.py`, `.gitkeep`) look identical in the
+    /// diff and are dropped too.
+    #[serde(default)]
+    pub skip_intent_to_add: bool,
+    /// Keep at most this many hunk lines per file, omitting the rest
+    ///
+    /// Whole hunks are kept where possible. Unset keeps every line.
+    #[serde(default)]
+    pub max_lines_per_file: Option<usize>,
+    /// Convert CRLF line endings in the diff to LF before it is measured and sent
+    #[serde(default)]
+    pub normalize_eol: bool,
+    /// File to write the generated message to before committing
+    /// Defaults to `<git dir>/COMMIT_MSG_GENERATED`
+    #[serde(default)]
+    pub message_file: Option<String>,
+    /// Include a `git diff --stat` summary before the diff in the prompt
+    #[serde(default)]
+    pub include_stat: bool,
+    /// Lines of context around each diff hunk (passed to git as `-U<n>`)
+    /// Defaults to 3, git's own default
+    #[serde(default = "default_context_lines")]
+    pub context_lines: usize,
+    /// How changed lines are shown: whole lines (default) or inline word changes
+    #[serde(default)]
+    pub diff_algorithm: DiffAlgorithm,
+    /// Number of times to retry a failed Claude invocation (default: 0)
+    #[serde(default)]
+    pub retries: u32,
+    /// Call Claude again right away when it exits successfully with an empty message
+    ///
+    /// Up to `retries` times (at least once, at most 3), without the delay.
+    #[serde(default)]
+    pub retry_on_empty: bool,
+    /// Delay before the first retry in milliseconds, doubled on each further retry
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// Maximum time in seconds to wait for a single Claude invocation
+    /// The process is killed when exceeded. Unset means no timeout
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Claude model to use (passed as `--model`); unset uses the CLI default
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Claude CLI executable name or path; the `CLAUDE_BINARY` environment variable takes precedence
+    #[serde(default)]
+    pub claude_binary: Option<String>,
+    /// Extra arguments appended to the `claude` command line
+    ///
+    /// Passed through verbatim without validation; an invalid flag will
+    /// make the Claude CLI fail.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// What to do when the prompt would exceed `max_prompt_size`
+    #[serde(default)]
+    pub on_oversize: OnOversize,
+    /// Summarize an oversized diff file by file, then generate the message from the summaries
+    ///
+    /// Takes precedence over `on_oversize`; each file costs one extra Claude call.
+    #[serde(default)]
+    pub summarize_large: bool,
+    /// Maximum estimated token count of the prompt, checked in addition to `max_prompt_size`
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    /// Number of candidate messages to generate and choose from (minimum 1)
+    #[serde(default = "default_candidates")]
+    pub candidates: usize,
+    /// Fail when the prompt references an unset `${VAR}` instead of expanding it to ""
+    #[serde(default)]
+    pub strict_env: bool,
+    /// Instructions sent to Claude as a system prompt, separate from `prompt` and the diff
+    ///
+    /// Counts toward `max_prompt_size`.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Language to write the message in; adds an instruction after the template
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Skeleton the message must follow, e.g. `{type}: {summary}\n\n{body}`
+    ///
+    /// Added to the prompt as the required output format.
+    #[serde(default)]
+    pub message_template: Option<String>,
+    /// Check generated messages against `message_template` (warn, or regenerate with `lint_retries`)
+    #[serde(default)]
+    pub check_template: bool,
+    /// Commit types Claude may use (e.g. `["feat", "fix"]`); empty allows any
+    ///
+    /// Listed in the prompt, and a message with another type is reported
+    /// (and regenerated with `lint_retries`).
+    #[serde(default)]
+    pub allowed_types: Vec<String>,
+    /// Check generated messages against the Conventional Commits format
+    #[serde(default)]
+    pub lint: bool,
+    /// Times to regenerate a message that fails the lint or template check (default: 0, only warn)
+    #[serde(default)]
+    pub lint_retries: u32,
+    /// Maximum subject line length in characters
+    ///
+    /// A longer subject is regenerated once with a stricter instruction,
+    /// then rejected. Unset means no limit.
+    #[serde(default)]
+    pub max_subject_length: Option<usize>,
+    /// Hard-wrap body lines longer than this many characters after generation
+    ///
+    /// The subject and fenced code blocks are never wrapped. Unset leaves the body as is.
+    #[serde(default)]
+    pub wrap_body: Option<usize>,
+    /// Number of recent commit subjects to include as a style reference (default: 0)
+    #[serde(default)]
+    pub style_context_count: usize,
+    /// Include the message in `.git/COMMIT_EDITMSG`, if any, for Claude to improve on
+    #[serde(default)]
+    pub reuse_editmsg: bool,
+    /// Text placed between the prompt and the diff (default: a blank line, `"\n\n"`)
+    #[serde(default)]
+    pub separator: Option<String>,
+    /// Wrap the diff in a ```` ```diff ```` fenced code block (default: false)
+    #[serde(default)]
+    pub fence_diff: bool,
+    /// Whether the prompt and other sections come before (default) or after the diff
+    #[serde(default)]
+    pub prompt_position: PromptPosition,
+    /// Whitespace removed from Claude's response after any outer code fence (default: trim)
+    #[serde(default)]
+    pub trim_output: TrimOutput,
+    /// Mask secrets (API keys, passwords, random tokens) in the diff, `--stat`
+    /// summary and recent commit subjects before sending them
+    #[serde(default)]
+    pub redact: bool,
+    /// Additional regexes whose matches are masked when `redact` is enabled
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+    /// GPG-sign commits (`git commit -S`); git's signing key must be configured
+    #[serde(default)]
+    pub sign: bool,
+    /// Git trailers appended to every generated message, as `Key: value` or `key=value`
+    #[serde(default)]
+    pub trailers: Vec<String>,
+    /// Maximum number of files in the diff; larger change sets are refused before calling Claude
+    #[serde(default)]
+    pub max_diff_files: Option<usize>,
+    /// Seconds a cached message stays valid (default: 86400; 0 disables the cache)
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// Reuse a cached message when the prompt is unchanged
+    ///
+    /// Not read from the config file: the CLI enables it unless `--no-cache`
+    /// is given, and library callers opt in explicitly.
+    #[serde(skip)]
+    pub use_cache: bool,
+    /// Print Claude's output while it is generated (`--stream`)
+    ///
+    /// Not read from the config file, like `use_cache`.
+    #[serde(skip)]
+    pub stream: bool,
+    /// Named alternative prompts selectable with `--profile`
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named prompt in the `[profiles.<name>]` table
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Profile {
+    /// Prompt template used instead of the top-level `prompt`
+    pub prompt: String,
+}
+
+/// Behavior when the prompt exceeds `max_prompt_size`
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnOversize {
+    /// Fail with an error (default)
+    #[default]
+    Error,
+    /// Truncate the diff on a line boundary and mark the truncation
+    Truncate,
+}
+
+/// Where the prompt template and other sections go relative to the diff
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptPosition {
+    /// Instructions first, then the diff (default)
+    #[default]
+    Before,
+    /// The diff first, then the instructions
+    After,
+}
+
+/// How surrounding whitespace is removed from Claude's response
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimOutput {
+    /// Remove leading and trailing whitespace (default)
+    #[default]
+    Trim,
+    /// Remove only trailing whitespace, keeping intentional leading indentation
+    TrimEnd,
+    /// Keep the response exactly as returned
+    None,
+}
+
+/// Granularity of the changes shown in the diff
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    /// Removed and added lines (`-`/`+`), git's default
+    #[default]
+    Line,
+    /// Changed words inline (`git diff --word-diff`), easier to read for prose
+    Word,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prompt: String::new(),
+            prompt_parts: Vec::new(),
+            prompt_file: None,
+            max_prompt_size: default_max_prompt_size(),
+            warn_threshold: default_warn_threshold(),
+            exclude_paths: Vec::new(),
+            strip_binary: false,
+            mark_new_files: false,
+            skip_intent_to_add: false,
+            max_lines_per_file: None,
+            normalize_eol: false,
+            message_file: None,
+            include_stat: false,
+            context_lines: default_context_lines(),
+            diff_algorithm: DiffAlgorithm::Line,
+            retries: 0,
+            retry_on_empty: false,
+            retry_delay_ms: default_retry_delay_ms(),
+            timeout_secs: None,
+            model: None,
+            claude_binary: None,
+            extra_args: Vec::new(),
+            on_oversize: OnOversize::Error,
+            summarize_large: false,
+            max_tokens: None,
+            candidates: default_candidates(),
+            strict_env: false,
+            system_prompt: None,
+            language: None,
+            message_template: None,
+            check_template: false,
+            allowed_types: Vec::new(),
+            lint: false,
+            lint_retries: 0,
+            max_subject_length: None,
+            wrap_body: None,
+            style_context_count: 0,
+            reuse_editmsg: false,
+            separator: None,
+            fence_diff: false,
+            prompt_position: PromptPosition::Before,
+            trim_output: TrimOutput::Trim,
+            redact: false,
+            redact_patterns: Vec::new(),
+            sign: false,
+            trailers: Vec::new(),
+            max_diff_files: None,
+            cache_ttl_secs: default_cache_ttl_secs(),
+            use_cache: false,
+            stream: false,
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+/// Default cache time-to-live: 24 hours
+fn default_cache_ttl_secs() -> u64 {
+    DEFAULT_CACHE_TTL_SECS
 }
 
 /// Default maximum prompt size: 1MB
@@ -35,6 +358,26 @@ fn default_max_prompt_size() -> usize {
     1_000_000
 }
 
+/// Default size warning threshold: 80% of `max_prompt_size`
+fn default_warn_threshold() -> f32 {
+    0.8
+}
+
+/// Default diff context lines: 3 (same as git)
+fn default_context_lines() -> usize {
+    3
+}
+
+/// Default delay before the first retry: 1 second
+fn default_retry_delay_ms() -> u64 {
+    1_000
+}
+
+/// Default number of candidate messages: 1
+fn default_candidates() -> usize {
+    1
+}
+
 /// Default content for a newly generated configuration file
 pub const DEFAULT_CONFIG_CONTENT: &str = r#"# claude_commit configuration file
 # Usage: claude_commit --config <path>  OR place this file at .claude_commit.toml
@@ -45,13 +388,240 @@ prompt = """
 メッセージのみを出力し、説明や追加のテキストは含めないでください。
 """
 
+# {branch} in the prompt is replaced with the current branch name (the short
+# commit hash on a detached HEAD), e.g. to take the commit type from "feat/login"
+
+# Optional: Read the prompt from a file instead (relative to this file's directory)
+# Set exactly one of prompt, prompt_parts and prompt_file
+# prompt_file = "prompts/commit.md"
+
+# Optional: Or compose the prompt from snippets, joined with newlines in order
+# prompt_parts = [
+#     "Generate a commit message for the following git diff.",
+#     "Use the Conventional Commits format.",
+# ]
+
 # Optional: Maximum combined size of prompt template and git diff in bytes
 # Default: 1,000,000 bytes (1MB)
-# Increase this value if you need to handle very large diffs
+# Increase this value if you need to handle very large diffs (or use --max-prompt-size once)
 # max_prompt_size = 1000000
+
+# Optional: Warn when the prompt uses more than this fraction of max_prompt_size
+# Default: 0.8 (80%); 1.0 disables the warning
+# warn_threshold = 0.8
+
+# Optional: Paths to leave out of the diff (e.g. lockfiles, generated code)
+# Patterns follow git's pathspec rules
+# exclude_paths = ["Cargo.lock", "package-lock.json"]
+
+# Optional: Replace binary file diffs with a one-line summary
+# strip_binary = true
+
+# Optional: Label newly added files with a [NEW FILE] line so Claude knows
+# the whole file is new rather than a large addition
+# mark_new_files = true
+
+# Optional: Leave out intent-to-add files (`git add -N`), which appear in the
+# staged diff as a new-file header with no content
+# Note: staged empty files (e.g. __init__.py, .gitkeep) look the same in the
+# diff and are left out as well
+# skip_intent_to_add = true
+
+# Optional: Keep at most this many lines of each file's hunks so one huge file
+# (migrations, snapshots) does not crowd out the rest of the diff
+# max_lines_per_file = 200
+
+# Optional: Convert CRLF line endings in the diff to LF (e.g. Windows checkouts),
+# so the prompt size is the same on every platform
+# normalize_eol = true
+
+# Optional: Where to write the generated message before committing
+# Default: <git dir>/COMMIT_MSG_GENERATED
+# message_file = "/tmp/claude_commit_msg"
+
+# Optional: Include a per-file change summary (git diff --stat) in the prompt
+# include_stat = true
+
+# Optional: Lines of context around each diff hunk
+# Default: 3. Set to 0 for minimal hunks to fit larger changes under max_prompt_size
+# context_lines = 3
+
+# Optional: Show changes as whole lines ("line", default) or inline words ("word")
+# "word" passes --word-diff to git, which reads better for docs and READMEs
+# diff_algorithm = "line"
+
+# Optional: Retry failed Claude invocations with exponential backoff
+# retries = 0
+# retry_delay_ms = 1000
+
+# Optional: When Claude succeeds but prints nothing, call it again right away
+# (up to `retries` times, at least once and at most 3) instead of failing
+# retry_on_empty = false
+
+# Optional: Kill the Claude process if it runs longer than this many seconds
+# timeout_secs = 120
+
+# Optional: Claude model to use (overridden by --model)
+# model = "sonnet"
+
+# Optional: Claude CLI executable name or path (overridden by the CLAUDE_BINARY environment variable)
+# Default: claude, looked up in PATH
+# claude_binary = "/opt/claude/bin/claude"
+
+# Optional: Extra arguments passed verbatim to the claude command (not validated)
+# extra_args = ["--allowedTools", "Read"]
+
+# Optional: What to do when the prompt exceeds max_prompt_size
+# "error" (default) fails, "truncate" cuts the diff and notes the truncation
+# on_oversize = "error"
+
+# Optional: Summarize an oversized diff file by file and generate the message
+# from the summaries (one extra Claude call per file; takes precedence over on_oversize)
+# summarize_large = false
+
+# Optional: Maximum estimated token count of the prompt (checked in addition to bytes)
+# max_tokens = 200000
+
+# Optional: Number of candidate messages to generate and choose from
+# candidates = 1
+
+# Optional: ${VAR} in the prompt is replaced with the environment variable's value
+# ($${ produces a literal ${). Unset variables expand to "" unless strict_env = true
+# strict_env = false
+
+# Optional: System prompt passed to Claude separately (--append-system-prompt)
+# The prompt above and the diff remain the user message. Counts toward max_prompt_size
+# system_prompt = "You are an expert at writing concise, accurate git commit messages."
+
+# Optional: Language to write the message in (overridden by --lang)
+# Adds "Write the commit message in <language>." after the prompt
+# language = "ja"
+
+# Optional: Skeleton the message must follow; {placeholders} are filled in by Claude
+# check_template warns when the subject or blank-line layout does not match
+# (lint_retries also applies)
+# message_template = "{type}: {summary}\n\n{body}\n\n{footer}"
+# check_template = true
+
+# Optional: Only allow these commit types. They are listed in the prompt, and a
+# message with another type is reported (lint_retries regenerates it)
+# allowed_types = ["feat", "fix", "docs", "refactor", "test", "chore"]
+
+# Optional: Check that messages follow Conventional Commits (type(scope): description,
+# subject <= 72 characters, blank line before the body). Violations are printed as warnings;
+# lint_retries asks Claude for a new message up to that many times
+# lint = true
+# lint_retries = 0
+
+# Optional: Maximum subject line length in characters (not bytes)
+# A longer subject is regenerated once with a stricter instruction, then rejected
+# max_subject_length = 50
+
+# Optional: Hard-wrap body lines at this many characters (the subject, blank lines
+# and fenced code blocks are left alone; list items keep a hanging indent)
+# wrap_body = 72
+
+# Optional: Include this many recent commit subjects so Claude matches the repository's style
+# style_context_count = 5
+
+# Optional: Include the last message in .git/COMMIT_EDITMSG (without # comment lines)
+# as a previous message for Claude to improve on, e.g. when amending
+# reuse_editmsg = true
+
+# Optional: Text placed between the prompt and the diff (default: a blank line)
+# separator = "\n\n## Diff\n"
+
+# Optional: Wrap the diff in a ```diff fenced code block (the fences count toward max_prompt_size)
+# fence_diff = false
+
+# Optional: Put the prompt before (default) or after the diff; some models follow
+# instructions better when they come last. The separator stays between the two
+# prompt_position = "before"
+
+# Optional: Whitespace to remove from Claude's response after stripping an outer
+# code fence: "trim" (default), "trim_end" (keep leading whitespace) or "none"
+# trim_output = "trim"
+
+# Optional: Mask secrets in the diff, --stat summary and recent commit subjects
+# with ***REDACTED*** before sending them to Claude
+# Covers AWS access keys, literal password/token/secret/api_key assignments and long random tokens
+# redact = true
+# Additional regular expressions to mask (only used with redact = true)
+# redact_patterns = ["ghp_[A-Za-z0-9]{36}"]
+
+# Optional: Sign commits with git commit -S (same as --sign)
+# Requires git's signing setup (user.signingkey, and gpg.format for SSH keys)
+# sign = false
+
+# Optional: Git trailers appended after a blank line (merged with --trailer)
+# trailers = ["Refs: #123"]
+
+# Optional: Refuse to generate a message when the diff changes more than this many files
+# max_diff_files = 50
+
+# Optional: Seconds to reuse a message generated for an identical prompt (0 disables the cache)
+# Cached messages are stored in .git/claude_commit_cache.json; --no-cache skips the lookup
+# cache_ttl_secs = 86400
+
+# Optional: Named prompts selectable with --profile <name> (the top-level prompt stays the default)
+# [profiles.fix]
+# prompt = "Generate a commit message for this bug fix, explaining the cause."
 "#;
 
-/// Load configuration from a TOML file
+/// Config file format, selected by file extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detect the format from `.toml`, `.json`, `.yaml` or `.yml`
+    ///
+    /// Files without an extension are read as TOML. Unknown extensions are
+    /// also read as TOML, with a warning.
+    pub fn from_path(path: &Path) -> Self {
+        let Some(ext) = path.extension() else {
+            return ConfigFormat::Toml;
+        };
+        match ext.to_string_lossy().to_lowercase().as_str() {
+            "toml" => ConfigFormat::Toml,
+            "json" => ConfigFormat::Json,
+            "yaml" | "yml" => ConfigFormat::Yaml,
+            other => {
+                log::warn(format!(
+                    "unknown config file extension '.{}', reading {} as TOML",
+                    other,
+                    path.display()
+                ));
+                ConfigFormat::Toml
+            }
+        }
+    }
+}
+
+/// Parse config file content in the given format
+fn parse_config(content: &str, format: ConfigFormat) -> Result<Config> {
+    match format {
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| parse_error("TOML", e)),
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|e| parse_error("JSON", e)),
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| parse_error("YAML", e)),
+    }
+}
+
+/// Wrap a deserializer error as [`Error::ConfigParse`]
+fn parse_error(
+    format: &'static str,
+    source: impl std::error::Error + Send + Sync + 'static,
+) -> Error {
+    Error::ConfigParse {
+        format,
+        source: Box::new(source),
+    }
+}
+
+/// Load configuration from a TOML, JSON or YAML file
 ///
 /// # Arguments
 ///
@@ -64,9 +634,16 @@ prompt = """
 /// # Errors
 ///
 /// * File does not exist
-/// * Invalid TOML format
-/// * Missing required fields
+/// * Invalid TOML / JSON / YAML format
+/// * Not exactly one of `prompt`, `prompt_parts` and `prompt_file` is set
+/// * `prompt_file` cannot be read
 /// * Prompt field is empty or whitespace-only
+/// * `max_prompt_size` is zero or too small to hold any prompt
+/// * Prompt references an unset environment variable with `strict_env = true`
+/// * A `CLAUDE_COMMIT_*` override has an invalid value
+///
+/// Values from [`PROMPT_ENV`], [`MAX_PROMPT_SIZE_ENV`] and [`MODEL_ENV`]
+/// take precedence over the file.
 ///
 /// # Example
 ///
@@ -80,25 +657,491 @@ prompt = """
 /// # }
 /// ```
 pub fn load_config(config_path: &str) -> Result<Config> {
-    let content = fs::read_to_string(config_path)
-        .context(format!("Failed to read config file: {}", config_path))?;
-    let config: Config = toml::from_str(&content).context("Failed to parse config file as TOML")?;
+    load_config_profile(config_path, None)
+}
+
+/// Load configuration, using the prompt of the named profile when given
+///
+/// With `profile` set to `None` this is the same as [`load_config`].
+///
+/// # Errors
+///
+/// Same as [`load_config`], and additionally:
+///
+/// * The profile does not exist (the error lists the available profiles)
+pub fn load_config_profile(config_path: &str, profile: Option<&str>) -> Result<Config> {
+    let content = fs::read_to_string(config_path).map_err(|source| Error::ConfigRead {
+        path: config_path.to_string(),
+        source,
+    })?;
+    let config = parse_config(&content, ConfigFormat::from_path(Path::new(config_path)))?;
+    finish_loading(config, config_path, profile, |name| {
+        std::env::var(name).ok()
+    })
+}
+
+/// Load several config files merged in order, later files overriding earlier ones
+///
+/// Keys set in a later file replace the same keys from earlier files, and
+/// tables such as `[profiles]` are merged key by key; arrays are replaced
+/// as a whole. Setting `prompt`, `prompt_parts` or `prompt_file` replaces all three, and a
+/// relative `prompt_file` is resolved against the directory of the file
+/// that sets it. Environment overrides, the profile and all checks (such as
+/// the empty-prompt check) apply to the merged result.
+///
+/// A single path behaves exactly like [`load_config_profile`]; no paths
+/// behaves like [`load_env_config`].
+///
+/// # Errors
+///
+/// Same as [`load_config_profile`], for any of the files or the merged result.
+pub fn load_configs(config_paths: &[String], profile: Option<&str>) -> Result<Config> {
+    match config_paths {
+        [] => return load_env_config(profile),
+        [config_path] => return load_config_profile(config_path, profile),
+        _ => {}
+    }
+
+    let mut merged = serde_json::Value::Object(serde_json::Map::new());
+    for config_path in config_paths {
+        let content = fs::read_to_string(config_path).map_err(|source| Error::ConfigRead {
+            path: config_path.to_string(),
+            source,
+        })?;
+        let format = ConfigFormat::from_path(Path::new(config_path));
+        // Type errors are reported against the file that contains them
+        parse_config(&content, format)?;
+        let mut layer = parse_config_value(&content, format)?;
+
+        if let Some(layer) = layer.as_object_mut()
+            && PROMPT_KEYS.iter().any(|key| layer.contains_key(*key))
+        {
+            if let Some(merged) = merged.as_object_mut() {
+                for key in PROMPT_KEYS {
+                    merged.remove(key);
+                }
+            }
+            if let Some(serde_json::Value::String(prompt_file)) = layer.get_mut("prompt_file") {
+                let base = Path::new(config_path).parent().unwrap_or(Path::new(""));
+                let resolved = std::path::absolute(base.join(&*prompt_file))
+                    .map_err(|e| Error::io("Failed to resolve prompt_file", e))?;
+                *prompt_file = resolved.to_string_lossy().to_string();
+            }
+        }
+        merge_config_values(&mut merged, layer);
+    }
+
+    let config = serde_json::from_value(merged).map_err(|e| parse_error("merged config", e))?;
+    finish_loading(config, &config_paths.join(", "), profile, |name| {
+        std::env::var(name).ok()
+    })
+}
+
+/// Parse config file content into a generic value for merging
+fn parse_config_value(content: &str, format: ConfigFormat) -> Result<serde_json::Value> {
+    match format {
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| parse_error("TOML", e)),
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|e| parse_error("JSON", e)),
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| parse_error("YAML", e)),
+    }
+}
+
+/// Merge `layer` into `base`: tables key by key, any other value replaced
+fn merge_config_values(base: &mut serde_json::Value, layer: serde_json::Value) {
+    match (base, layer) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_config_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Load configuration from `CLAUDE_COMMIT_*` environment variables alone
+///
+/// Used when no config file exists but [`PROMPT_ENV`] is set, e.g. in
+/// containers. Fields without an environment variable keep their defaults.
+///
+/// # Errors
+///
+/// * An override has an invalid value
+/// * The prompt is empty, or `profile` is given (there are no profiles)
+pub fn load_env_config(profile: Option<&str>) -> Result<Config> {
+    finish_loading(Config::default(), ENV_CONFIG_SOURCE, profile, |name| {
+        std::env::var(name).ok()
+    })
+}
+
+/// Apply environment overrides, the profile and checks to a parsed config
+///
+/// `config_path` names where the config came from in error messages.
+fn finish_loading(
+    mut config: Config,
+    config_path: &str,
+    profile: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Config> {
+    apply_env_overrides(&mut config, env)?;
+    resolve_prompt_source(&mut config, config_path)?;
+    if let Some(name) = profile {
+        select_profile(&mut config, name, config_path)?;
+    }
+    config.prompt = expand_prompt_env(&config.prompt, config.strict_env, config_path)?;
+
+    validate_config(&config, config_path)?;
+
+    Ok(config)
+}
+
+/// Config keys that each set the prompt; exactly one may be used
+const PROMPT_KEYS: [&str; 3] = ["prompt", "prompt_parts", "prompt_file"];
+
+/// Environment variable that overrides `prompt` (and any `prompt_parts` or `prompt_file`)
+pub const PROMPT_ENV: &str = "CLAUDE_COMMIT_PROMPT";
+
+/// Environment variable that overrides `max_prompt_size`
+pub const MAX_PROMPT_SIZE_ENV: &str = "CLAUDE_COMMIT_MAX_PROMPT_SIZE";
+
+/// Environment variable that overrides `model`
+pub const MODEL_ENV: &str = "CLAUDE_COMMIT_MODEL";
 
+/// Config source named in errors when there is no config file
+const ENV_CONFIG_SOURCE: &str = "environment variables";
+
+/// Override config file values with `CLAUDE_COMMIT_*` environment variables
+///
+/// `env` looks up a variable; unset and empty variables are ignored.
+/// Precedence is CLI flags > environment > config file > defaults; the CLI
+/// flags are applied later by the binary.
+///
+/// # Errors
+///
+/// * [`MAX_PROMPT_SIZE_ENV`] is not a positive number
+fn apply_env_overrides(config: &mut Config, env: impl Fn(&str) -> Option<String>) -> Result<()> {
+    let env = |name: &str| env(name).filter(|value| !value.is_empty());
+
+    if let Some(prompt) = env(PROMPT_ENV) {
+        config.prompt = prompt;
+        config.prompt_parts.clear();
+        config.prompt_file = None;
+    }
+    if let Some(value) = env(MAX_PROMPT_SIZE_ENV) {
+        config.max_prompt_size = value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&size: &usize| size > 0)
+            .ok_or_else(|| Error::InvalidEnvVar {
+                name: MAX_PROMPT_SIZE_ENV.to_string(),
+                value,
+            })?;
+    }
+    if let Some(model) = env(MODEL_ENV) {
+        config.model = Some(model);
+    }
+    Ok(())
+}
+
+/// Set `prompt` from whichever of `prompt`, `prompt_parts` and `prompt_file` is used
+///
+/// `prompt_parts` are joined with newlines in order. A relative `prompt_file`
+/// is resolved against the directory of the config file.
+fn resolve_prompt_source(config: &mut Config, config_path: &str) -> Result<()> {
+    let set: Vec<&str> = PROMPT_KEYS
+        .into_iter()
+        .zip([
+            !config.prompt.is_empty(),
+            !config.prompt_parts.is_empty(),
+            config.prompt_file.is_some(),
+        ])
+        .filter_map(|(key, is_set)| is_set.then_some(key))
+        .collect();
+    let invalid = match set.as_slice() {
+        [_] => None,
+        [] => Some("one of 'prompt', 'prompt_parts' or 'prompt_file' must be set".to_string()),
+        _ => Some(format!(
+            "only one of 'prompt', 'prompt_parts' and 'prompt_file' can be set (found '{}')",
+            set.join("', '")
+        )),
+    };
+    if let Some(message) = invalid {
+        return Err(Error::InvalidConfig {
+            path: config_path.to_string(),
+            message,
+        });
+    }
+
+    if !config.prompt_parts.is_empty() {
+        config.prompt = config.prompt_parts.join("\n");
+        return Ok(());
+    }
+    let Some(prompt_file) = &config.prompt_file else {
+        return Ok(());
+    };
+
+    let base = Path::new(config_path).parent().unwrap_or(Path::new(""));
+    let path = base.join(prompt_file);
+    config.prompt = fs::read_to_string(&path)
+        .map_err(|e| Error::io(format!("Failed to read prompt file: {}", path.display()), e))?;
+    Ok(())
+}
+
+/// Check loaded values that deserialization alone cannot reject
+fn validate_config(config: &Config, config_path: &str) -> Result<()> {
     // Validate prompt is not empty or whitespace-only
     if config.prompt.trim().is_empty() {
-        anyhow::bail!(
-            "Configuration error: 'prompt' field cannot be empty or whitespace-only. \
-             Please provide a valid prompt template in {}",
-            config_path
+        return Err(Error::EmptyPrompt {
+            path: config_path.to_string(),
+        });
+    }
+
+    // Reject invalid redact patterns now rather than after running git
+    Redactor::new(&config.redact_patterns)?;
+
+    // A limit that cannot even hold the prompt/diff separator rejects every diff
+    if config.max_prompt_size <= SEPARATOR.len() {
+        return Err(Error::InvalidConfig {
+            path: config_path.to_string(),
+            message: format!(
+                "'max_prompt_size' must be greater than {} bytes (got {})",
+                SEPARATOR.len(),
+                config.max_prompt_size
+            ),
+        });
+    }
+
+    if let Some(trailer) = config.trailers.iter().find(|t| parse_trailer(t).is_none()) {
+        return Err(Error::InvalidConfig {
+            path: config_path.to_string(),
+            message: format!(
+                "invalid trailer '{}' in 'trailers' (expected 'Key: value')",
+                trailer
+            ),
+        });
+    }
+
+    if config.wrap_body == Some(0) {
+        return Err(Error::InvalidConfig {
+            path: config_path.to_string(),
+            message: "'wrap_body' must be at least 1".to_string(),
+        });
+    }
+
+    if config.max_lines_per_file == Some(0) {
+        return Err(Error::InvalidConfig {
+            path: config_path.to_string(),
+            message: "'max_lines_per_file' must be at least 1".to_string(),
+        });
+    }
+
+    if !(0.0..=1.0).contains(&config.warn_threshold) {
+        return Err(Error::InvalidConfig {
+            path: config_path.to_string(),
+            message: format!(
+                "'warn_threshold' must be between 0.0 and 1.0 (got {})",
+                config.warn_threshold
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Replace the top-level prompt with the prompt of profile `name`
+///
+/// `config_path` names the config in the error for an unknown profile.
+fn select_profile(config: &mut Config, name: &str, config_path: &str) -> Result<()> {
+    match config.profiles.get(name) {
+        Some(profile) => {
+            config.prompt = profile.prompt.clone();
+            Ok(())
+        }
+        None => Err(Error::ProfileNotFound {
+            name: name.to_string(),
+            available: config.profiles.keys().cloned().collect(),
+            path: config_path.to_string(),
+        }),
+    }
+}
+
+/// Expand `${VAR}` references in a template with environment variable values
+///
+/// `$${` yields a literal `${`. A `$` not followed by `{`, and an unterminated
+/// `${`, are left as-is.
+///
+/// # Errors
+///
+/// * A referenced variable is unset (or not valid unicode) and `strict` is true
+pub fn expand_env(template: &str, strict: bool) -> Result<String> {
+    expand_with(template, strict, |name| std::env::var(name).ok())
+}
+
+/// [`expand_env`] for the prompt of the config at `config_path`, naming the file in errors
+fn expand_prompt_env(prompt: &str, strict: bool, config_path: &str) -> Result<String> {
+    expand_env(prompt, strict).map_err(|e| with_config_path(e, config_path))
+}
+
+/// Record `config_path` in an [`Error::EnvVarNotSet`]; other errors are returned as is
+fn with_config_path(error: Error, config_path: &str) -> Error {
+    match error {
+        Error::EnvVarNotSet { name, .. } => Error::EnvVarNotSet {
+            name,
+            path: Some(config_path.to_string()),
+        },
+        other => other,
+    }
+}
+
+/// Expand `${VAR}` references using `lookup` to resolve variable values
+fn expand_with(
+    template: &str,
+    strict: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if let Some(after) = tail.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                expanded.push_str(tail);
+                return Ok(expanded);
+            };
+            let name = &after[..end];
+            match lookup(name) {
+                Some(value) => expanded.push_str(&value),
+                None if strict => {
+                    return Err(Error::EnvVarNotSet {
+                        name: name.to_string(),
+                        path: None,
+                    });
+                }
+                None => {}
+            }
+            rest = &after[end + 1..];
+        } else {
+            expanded.push('$');
+            rest = &tail[1..];
+        }
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Shared config committed to a repository, relative to its top-level directory
+pub const REPO_CONFIG_FILE: &str = ".claude_commit/prompt.toml";
+
+/// Resolve the config files to load
+///
+/// Explicit `--config` paths are used as given and skip the search. Otherwise
+/// the file found by [`find_config`] is used; when there is none and
+/// `CLAUDE_COMMIT_PROMPT` is set, no file is needed (empty list).
+///
+/// # Errors
+///
+/// * No config file exists and `CLAUDE_COMMIT_PROMPT` is not set
+pub fn resolve_config_paths(explicit: Vec<String>) -> Result<Vec<String>> {
+    resolve_config_paths_with(
+        explicit,
+        find_config,
+        std::env::var_os(PROMPT_ENV).is_some(),
+    )
+}
+
+/// [`resolve_config_paths`] with the search and the environment check injected
+fn resolve_config_paths_with(
+    explicit: Vec<String>,
+    find: impl FnOnce() -> Result<PathBuf>,
+    prompt_env_set: bool,
+) -> Result<Vec<String>> {
+    if !explicit.is_empty() {
+        return Ok(explicit);
+    }
+    match find() {
+        Ok(path) => Ok(vec![path.to_string_lossy().to_string()]),
+        Err(Error::ConfigNotFound { .. }) if prompt_env_set => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Find the config file to use when `--config` is not given
+///
+/// Search order (first existing file wins):
+/// 1. `./prompt.toml`
+/// 2. `./.claude_commit.toml`
+/// 3. `<git root>/.claude_commit/prompt.toml` (shared through the repository)
+/// 4. `<git root>/.claude_commit.toml`
+/// 5. `$XDG_CONFIG_HOME/claude_commit/config.toml`
+/// 6. `$HOME/.config/claude_commit/config.toml`
+///
+/// # Errors
+///
+/// Returns an error listing every searched location when none exists.
+pub fn find_config() -> Result<PathBuf> {
+    let candidates = config_search_paths(
+        std::env::var("XDG_CONFIG_HOME").ok(),
+        std::env::var("HOME").ok(),
+        get_git_root().ok(),
+    );
+    first_existing(&candidates)
+}
+
+/// Build the list of config locations in search order
+fn config_search_paths(
+    xdg_config_home: Option<String>,
+    home: Option<String>,
+    git_root: Option<PathBuf>,
+) -> Vec<PathBuf> {
+    let mut paths = vec![
+        PathBuf::from("prompt.toml"),
+        PathBuf::from(".claude_commit.toml"),
+    ];
+    if let Some(root) = git_root {
+        paths.push(root.join(REPO_CONFIG_FILE));
+        paths.push(root.join(".claude_commit.toml"));
+    }
+    if let Some(xdg) = xdg_config_home.filter(|dir| !dir.is_empty()) {
+        paths.push(PathBuf::from(xdg).join("claude_commit").join("config.toml"));
+    }
+    if let Some(home) = home {
+        paths.push(
+            PathBuf::from(home)
+                .join(".config")
+                .join("claude_commit")
+                .join("config.toml"),
         );
     }
+    paths
+}
 
-    Ok(config)
+/// Return the first path that exists, or an error listing all of them
+fn first_existing(paths: &[PathBuf]) -> Result<PathBuf> {
+    if let Some(path) = paths.iter().find(|path| path.exists()) {
+        return Ok(path.clone());
+    }
+
+    Err(Error::ConfigNotFound {
+        searched: paths.to_vec(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
     #[test]
     fn test_config_deserialize_valid_toml() {
         // Arrange - valid TOML string
@@ -116,17 +1159,218 @@ prompt = "Generate a concise commit message:"
     }
 
     #[test]
-    fn test_config_deserialize_missing_prompt_field() {
-        // Arrange - TOML without prompt field
-        let toml_str = r#"
+    fn test_config_missing_prompt_and_prompt_file() {
+        // Arrange - TOML without prompt or prompt_file
+        let mut config: Config = toml::from_str(
+            r#"
 other_field = "value"
-"#;
+"#,
+        )
+        .unwrap();
 
         // Act
-        let result: Result<Config, _> = toml::from_str(toml_str);
+        let result = resolve_prompt_source(&mut config, "prompt.toml");
 
-        // Assert - should return error (prompt is required)
-        assert!(result.is_err());
+        // Assert - should return error (one of them is required)
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("'prompt_parts' or 'prompt_file' must be set"));
+    }
+
+    #[test]
+    fn test_config_prompt_and_prompt_file_both_set() {
+        // Arrange
+        let mut config: Config = toml::from_str(
+            r#"
+prompt = "inline"
+prompt_file = "prompt.md"
+"#,
+        )
+        .unwrap();
+
+        // Act
+        let result = resolve_prompt_source(&mut config, "prompt.toml");
+
+        // Assert
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("found 'prompt', 'prompt_file'"));
+    }
+
+    #[test]
+    fn test_config_prompt_parts_joined_in_order() {
+        // Arrange
+        let mut config: Config = toml::from_str(
+            r#"
+prompt_parts = ["Generate a commit message.", "Use English.", "Be concise."]
+"#,
+        )
+        .unwrap();
+
+        // Act
+        let result = resolve_prompt_source(&mut config, "prompt.toml");
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            config.prompt,
+            "Generate a commit message.\nUse English.\nBe concise."
+        );
+    }
+
+    #[test]
+    fn test_config_prompt_parts_exclusive_with_other_sources() {
+        // Arrange
+        let with_prompt: Config = toml::from_str(
+            r#"
+prompt = "inline"
+prompt_parts = ["part"]
+"#,
+        )
+        .unwrap();
+        let with_all: Config = toml::from_str(
+            r#"
+prompt = "inline"
+prompt_parts = ["part"]
+prompt_file = "prompt.md"
+"#,
+        )
+        .unwrap();
+
+        // Act
+        let errors = [with_prompt, with_all].map(|mut config| {
+            resolve_prompt_source(&mut config, "prompt.toml")
+                .unwrap_err()
+                .to_string()
+        });
+
+        // Assert
+        assert!(errors[0].contains("found 'prompt', 'prompt_parts'"));
+        assert!(errors[1].contains("found 'prompt', 'prompt_parts', 'prompt_file'"));
+    }
+
+    #[test]
+    fn test_config_inline_prompt_without_prompt_file() {
+        // Arrange
+        let mut config: Config = toml::from_str(r#"prompt = "inline""#).unwrap();
+
+        // Act
+        let result = resolve_prompt_source(&mut config, "prompt.toml");
+
+        // Assert - inline prompt is kept as-is
+        assert!(result.is_ok());
+        assert_eq!(config.prompt, "inline");
+    }
+
+    #[test]
+    fn test_config_prompt_file_missing() {
+        // Arrange
+        let mut config: Config = toml::from_str(r#"prompt_file = "missing.md""#).unwrap();
+
+        // Act
+        let result = resolve_prompt_source(&mut config, "/nonexistent/dir/prompt.toml");
+
+        // Assert - the path is resolved against the config file's directory
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("/nonexistent/dir/missing.md"));
+    }
+
+    #[test]
+    fn test_load_config_reads_prompt_file_relative_to_config() {
+        // Arrange
+        let dir = TempDir::new("prompt_file");
+        fs::create_dir_all(dir.join("prompts")).unwrap();
+        fs::write(dir.join("prompts/commit.md"), "# Commit\nGenerate:\n").unwrap();
+        let config_path = dir.join("config.toml");
+        fs::write(&config_path, r#"prompt_file = "prompts/commit.md""#).unwrap();
+
+        // Act
+        let config = load_config(config_path.to_str().unwrap());
+
+        // Assert
+        assert_eq!(config.unwrap().prompt, "# Commit\nGenerate:\n");
+    }
+
+    #[test]
+    fn test_load_configs_later_file_overrides() {
+        // Arrange - team settings, then personal overrides in another format
+        let dir = TempDir::new("layered");
+        let team = dir.join("team.toml");
+        fs::write(
+            &team,
+            r#"
+prompt = "Team prompt"
+max_prompt_size = 5000
+model = "team-model"
+
+[profiles.fix]
+prompt = "Team fix"
+"#,
+        )
+        .unwrap();
+        let personal = dir.join("personal.json");
+        fs::write(
+            &personal,
+            r#"{"max_prompt_size": 8000, "profiles": {"docs": {"prompt": "My docs"}}}"#,
+        )
+        .unwrap();
+        let paths = [
+            team.to_string_lossy().to_string(),
+            personal.to_string_lossy().to_string(),
+        ];
+
+        // Act
+        let config = load_configs(&paths, None);
+
+        // Assert - only the keys set later change; tables merge key by key
+        let config = config.unwrap();
+        assert_eq!(config.prompt, "Team prompt");
+        assert_eq!(config.max_prompt_size, 8000);
+        assert_eq!(config.model.as_deref(), Some("team-model"));
+        assert_eq!(
+            config.profiles.keys().collect::<Vec<_>>(),
+            vec!["docs", "fix"]
+        );
+    }
+
+    #[test]
+    fn test_load_configs_later_prompt_file_replaces_prompt() {
+        // Arrange - the prompt file is relative to the file that sets it
+        let dir = TempDir::new("layered_prompt");
+        fs::create_dir_all(dir.join("me")).unwrap();
+        fs::write(dir.join("me/prompt.md"), "My prompt").unwrap();
+        let team = dir.join("team.toml");
+        fs::write(&team, r#"prompt = "Team prompt""#).unwrap();
+        let personal = dir.join("me/config.toml");
+        fs::write(&personal, r#"prompt_file = "prompt.md""#).unwrap();
+        let paths = [
+            team.to_string_lossy().to_string(),
+            personal.to_string_lossy().to_string(),
+        ];
+
+        // Act
+        let config = load_configs(&paths, None);
+
+        // Assert - no "both set" error
+        assert_eq!(config.unwrap().prompt, "My prompt");
+    }
+
+    #[test]
+    fn test_load_configs_validates_merged_prompt() {
+        // Arrange - the later file blanks the prompt
+        let dir = TempDir::new("layered_empty");
+        let team = dir.join("team.toml");
+        fs::write(&team, r#"prompt = "Team prompt""#).unwrap();
+        let personal = dir.join("personal.toml");
+        fs::write(&personal, r#"prompt = "   ""#).unwrap();
+        let paths = [
+            team.to_string_lossy().to_string(),
+            personal.to_string_lossy().to_string(),
+        ];
+
+        // Act
+        let result = load_configs(&paths, None);
+
+        // Assert
+        assert!(matches!(result, Err(Error::EmptyPrompt { .. })));
     }
 
     #[test]
@@ -200,4 +1444,633 @@ prompt = "Use 日本語 and emojis 🎉 in message. Escape \"quotes\" and \ttabs
         assert!(config.prompt.contains("🎉"));
         assert!(config.prompt.contains("\"quotes\""));
     }
+
+    #[test]
+    fn test_config_deserialize_exclude_paths() {
+        // Arrange - TOML with exclude list
+        let toml_str = r#"
+prompt = "Generate:"
+exclude_paths = ["Cargo.lock", "*.min.js"]
+"#;
+
+        // Act
+        let config: Config = toml::from_str(toml_str).unwrap();
+
+        // Assert - patterns are kept verbatim
+        assert_eq!(config.exclude_paths, vec!["Cargo.lock", "*.min.js"]);
+    }
+
+    #[test]
+    fn test_config_deserialize_exclude_paths_defaults_to_empty() {
+        // Arrange - TOML without exclude list
+        let toml_str = r#"
+prompt = "Generate:"
+"#;
+
+        // Act
+        let config: Config = toml::from_str(toml_str).unwrap();
+
+        // Assert
+        assert!(config.exclude_paths.is_empty());
+    }
+
+    #[test]
+    fn test_config_deserialize_context_lines() {
+        // Arrange
+        let with_value = "prompt = \"Generate:\"\ncontext_lines = 0\n";
+        let without_value = "prompt = \"Generate:\"\n";
+
+        // Act
+        let configured: Config = toml::from_str(with_value).unwrap();
+        let defaulted: Config = toml::from_str(without_value).unwrap();
+
+        // Assert - explicit value kept, default matches git's 3 lines
+        assert_eq!(configured.context_lines, 0);
+        assert_eq!(defaulted.context_lines, 3);
+    }
+
+    #[test]
+    fn test_config_deserialize_diff_algorithm() {
+        // Arrange
+        let word = "prompt = \"Generate:\"\ndiff_algorithm = \"word\"\n";
+        let unset = "prompt = \"Generate:\"\n";
+
+        // Act / Assert
+        let config: Config = toml::from_str(word).unwrap();
+        assert_eq!(config.diff_algorithm, DiffAlgorithm::Word);
+        let config: Config = toml::from_str(unset).unwrap();
+        assert_eq!(config.diff_algorithm, DiffAlgorithm::Line);
+    }
+
+    #[test]
+    fn test_config_deserialize_on_oversize() {
+        // Arrange
+        let truncate = "prompt = \"Generate:\"\non_oversize = \"truncate\"\n";
+        let unset = "prompt = \"Generate:\"\n";
+        let invalid = "prompt = \"Generate:\"\non_oversize = \"shrink\"\n";
+
+        // Act / Assert - fail-fast stays the default, unknown values are rejected
+        let config: Config = toml::from_str(truncate).unwrap();
+        assert_eq!(config.on_oversize, OnOversize::Truncate);
+        let config: Config = toml::from_str(unset).unwrap();
+        assert_eq!(config.on_oversize, OnOversize::Error);
+        assert!(toml::from_str::<Config>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_config_search_paths_order() {
+        // Arrange / Act
+        let paths = config_search_paths(
+            Some("/xdg".to_string()),
+            Some("/home/user".to_string()),
+            Some(PathBuf::from("/repo")),
+        );
+
+        // Assert - local files first, then the repository root, then user config
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("prompt.toml"),
+                PathBuf::from(".claude_commit.toml"),
+                PathBuf::from("/repo/.claude_commit/prompt.toml"),
+                PathBuf::from("/repo/.claude_commit.toml"),
+                PathBuf::from("/xdg/claude_commit/config.toml"),
+                PathBuf::from("/home/user/.config/claude_commit/config.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_search_paths_skips_unset_locations() {
+        // Arrange / Act - empty XDG_CONFIG_HOME is treated as unset
+        let paths = config_search_paths(Some(String::new()), None, None);
+
+        // Assert
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("prompt.toml"),
+                PathBuf::from(".claude_commit.toml")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_paths_explicit_skips_search() {
+        // Arrange - a committed repo config must not override --config
+        let explicit = vec!["team.toml".to_string(), "mine.toml".to_string()];
+
+        // Act
+        let paths = resolve_config_paths_with(
+            explicit.clone(),
+            || panic!("the search must not run with --config"),
+            false,
+        );
+
+        // Assert
+        assert_eq!(paths.unwrap(), explicit);
+    }
+
+    #[test]
+    fn test_resolve_config_paths_uses_repo_config() {
+        // Arrange
+        let found = PathBuf::from("/repo/.claude_commit/prompt.toml");
+
+        // Act
+        let paths = resolve_config_paths_with(Vec::new(), || Ok(found.clone()), false);
+
+        // Assert
+        assert_eq!(paths.unwrap(), vec!["/repo/.claude_commit/prompt.toml"]);
+    }
+
+    #[test]
+    fn test_resolve_config_paths_not_found() {
+        // Arrange
+        let not_found = || {
+            Err(Error::ConfigNotFound {
+                searched: Vec::new(),
+            })
+        };
+
+        // Act
+        let with_env = resolve_config_paths_with(Vec::new(), not_found, true);
+        let without_env = resolve_config_paths_with(Vec::new(), not_found, false);
+
+        // Assert - CLAUDE_COMMIT_PROMPT alone is enough to run
+        assert!(with_env.unwrap().is_empty());
+        assert!(matches!(
+            without_env.unwrap_err(),
+            Error::ConfigNotFound { .. }
+        ));
+    }
+
+    #[test]
+    fn test_first_existing_returns_first_match() {
+        // Arrange
+        let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let paths = vec![PathBuf::from("/nonexistent/config.toml"), manifest.clone()];
+
+        // Act
+        let result = first_existing(&paths);
+
+        // Assert
+        assert_eq!(result.unwrap(), manifest);
+    }
+
+    #[test]
+    fn test_first_existing_lists_searched_locations() {
+        // Arrange
+        let paths = vec![
+            PathBuf::from("/nonexistent/a.toml"),
+            PathBuf::from("/nonexistent/b.toml"),
+        ];
+
+        // Act
+        let err = first_existing(&paths).unwrap_err().to_string();
+
+        // Assert
+        assert!(err.contains("No configuration file found"));
+        assert!(err.contains("/nonexistent/a.toml"));
+        assert!(err.contains("/nonexistent/b.toml"));
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "TICKET_PREFIX" => Some("PROJ".to_string()),
+            "TEAM" => Some("core".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_with_replaces_variables() {
+        // Arrange
+        let template = "Prefix subjects with ${TICKET_PREFIX}- (team: ${TEAM}).";
+
+        // Act
+        let result = expand_with(template, false, lookup);
+
+        // Assert
+        assert_eq!(result.unwrap(), "Prefix subjects with PROJ- (team: core).");
+    }
+
+    #[test]
+    fn test_expand_with_missing_variable_lenient() {
+        // Arrange / Act
+        let result = expand_with("[${MISSING}]", false, lookup);
+
+        // Assert - unset variables expand to an empty string
+        assert_eq!(result.unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_expand_with_missing_variable_strict() {
+        // Arrange / Act
+        let result = expand_with("[${MISSING}]", true, lookup);
+
+        // Assert
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("'MISSING'"));
+    }
+
+    #[test]
+    fn test_with_config_path_names_the_file() {
+        // Arrange
+        let error = expand_with("${MISSING}", true, lookup).unwrap_err();
+
+        // Act
+        let err = with_config_path(error, "prompt.toml").to_string();
+
+        // Assert
+        assert_eq!(
+            err,
+            "Environment variable 'MISSING' is not set (referenced by the prompt in prompt.toml)"
+        );
+    }
+
+    #[test]
+    fn test_expand_with_escape_yields_literal() {
+        // Arrange / Act
+        let result = expand_with("$${TICKET_PREFIX} costs $5, ${TEAM}", true, lookup);
+
+        // Assert - escaped reference is not expanded, lone $ is kept
+        assert_eq!(result.unwrap(), "${TICKET_PREFIX} costs $5, core");
+    }
+
+    #[test]
+    fn test_expand_with_unterminated_reference_kept() {
+        // Arrange / Act
+        let result = expand_with("keep ${TEAM", true, lookup);
+
+        // Assert
+        assert_eq!(result.unwrap(), "keep ${TEAM");
+    }
+
+    #[test]
+    fn test_expand_env_reads_environment() {
+        // Arrange - PATH is set in any test environment
+        let path = std::env::var("PATH").unwrap();
+
+        // Act
+        let result = expand_env("${PATH}", true);
+
+        // Assert
+        assert_eq!(result.unwrap(), path);
+    }
+
+    const PROFILES_TOML: &str = r#"
+prompt = "default prompt"
+
+[profiles.feat]
+prompt = "feature prompt"
+
+[profiles.fix]
+prompt = "fix prompt"
+"#;
+
+    #[test]
+    fn test_select_profile_replaces_prompt() {
+        // Arrange
+        let mut config: Config = toml::from_str(PROFILES_TOML).unwrap();
+
+        // Act
+        let result = select_profile(&mut config, "fix", "prompt.toml");
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(config.prompt, "fix prompt");
+    }
+
+    #[test]
+    fn test_select_profile_missing_lists_available() {
+        // Arrange
+        let mut config: Config = toml::from_str(PROFILES_TOML).unwrap();
+
+        // Act
+        let err = select_profile(&mut config, "chore", "prompt.toml")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert!(err.contains("'chore'"));
+        assert!(err.contains("prompt.toml"));
+        assert!(err.contains("feat, fix"));
+        assert_eq!(config.prompt, "default prompt");
+    }
+
+    #[test]
+    fn test_config_without_profiles_keeps_prompt() {
+        // Arrange / Act
+        let config: Config = toml::from_str(r#"prompt = "only prompt""#).unwrap();
+
+        // Assert
+        assert_eq!(config.prompt, "only prompt");
+        assert!(config.profiles.is_empty());
+    }
+
+    #[test]
+    fn test_config_format_from_path() {
+        // Arrange / Act / Assert
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.yaml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.YML")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.conf")),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn test_parse_config_formats_are_equivalent() {
+        // Arrange - the same settings in each format
+        let toml_str = r#"
+prompt = "Generate a message"
+exclude_paths = ["Cargo.lock"]
+on_oversize = "truncate"
+
+[profiles.fix]
+prompt = "Fix prompt"
+"#;
+        let json_str = r#"{
+  "prompt": "Generate a message",
+  "exclude_paths": ["Cargo.lock"],
+  "on_oversize": "truncate",
+  "profiles": { "fix": { "prompt": "Fix prompt" } }
+}"#;
+        let yaml_str = r#"
+prompt: Generate a message
+exclude_paths:
+  - Cargo.lock
+on_oversize: truncate
+profiles:
+  fix:
+    prompt: Fix prompt
+"#;
+
+        // Act
+        let from_toml = parse_config(toml_str, ConfigFormat::Toml).unwrap();
+        let from_json = parse_config(json_str, ConfigFormat::Json).unwrap();
+        let from_yaml = parse_config(yaml_str, ConfigFormat::Yaml).unwrap();
+
+        // Assert
+        assert_eq!(from_toml.on_oversize, OnOversize::Truncate);
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_toml, from_yaml);
+    }
+
+    #[test]
+    fn test_parse_config_reports_format_in_error() {
+        // Arrange / Act
+        let err = parse_config("prompt: [", ConfigFormat::Yaml).unwrap_err();
+
+        // Assert
+        assert!(err.to_string().contains("YAML"));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_zero_max_prompt_size() {
+        // Arrange
+        let config: Config = toml::from_str(
+            r#"
+prompt = "Generate a message"
+max_prompt_size = 0
+"#,
+        )
+        .unwrap();
+
+        // Act
+        let err = validate_config(&config, "prompt.toml")
+            .unwrap_err()
+            .to_string();
+
+        // Assert - names the field and the config path
+        assert!(err.contains("max_prompt_size"));
+        assert!(err.contains("prompt.toml"));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_invalid_redact_pattern() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate a message".to_string(),
+            redact_patterns: vec!["[unclosed".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let result = validate_config(&config, "prompt.toml");
+
+        // Assert
+        assert!(matches!(result, Err(Error::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_warn_threshold_out_of_range() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate a message".to_string(),
+            warn_threshold: 1.5,
+            ..Default::default()
+        };
+
+        // Act
+        let err = validate_config(&config, "prompt.toml")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert!(err.contains("warn_threshold"));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_zero_wrap_body() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate a message".to_string(),
+            wrap_body: Some(0),
+            ..Default::default()
+        };
+
+        // Act
+        let err = validate_config(&config, "prompt.toml")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert!(err.contains("wrap_body"));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_zero_max_lines_per_file() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate a message".to_string(),
+            max_lines_per_file: Some(0),
+            ..Default::default()
+        };
+
+        // Act
+        let err = validate_config(&config, "prompt.toml")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert!(err.contains("max_lines_per_file"));
+    }
+
+    #[test]
+    fn test_validate_config_rejects_invalid_trailer() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate a message".to_string(),
+            trailers: vec!["Refs: #1".to_string(), "Refs".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let err = validate_config(&config, "prompt.toml")
+            .unwrap_err()
+            .to_string();
+
+        // Assert - names the offending entry
+        assert!(err.contains("invalid trailer 'Refs'"));
+    }
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> + use<> {
+        let vars: BTreeMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn file_config() -> Config {
+        toml::from_str(
+            r#"
+prompt = "From file"
+max_prompt_size = 5000
+model = "sonnet"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_env_overrides_prompt() {
+        // Arrange
+        let env = env_from(&[(PROMPT_ENV, "From env")]);
+
+        // Act
+        let config = finish_loading(file_config(), "prompt.toml", None, env).unwrap();
+
+        // Assert
+        assert_eq!(config.prompt, "From env");
+        assert_eq!(config.max_prompt_size, 5000);
+    }
+
+    #[test]
+    fn test_env_overrides_prompt_file() {
+        // Arrange - the file only names a prompt file, which is never read
+        let config: Config = toml::from_str("prompt_file = \"missing.md\"\n").unwrap();
+        let env = env_from(&[(PROMPT_ENV, "From env")]);
+
+        // Act
+        let config = finish_loading(config, "prompt.toml", None, env).unwrap();
+
+        // Assert
+        assert_eq!(config.prompt, "From env");
+        assert_eq!(config.prompt_file, None);
+    }
+
+    #[test]
+    fn test_env_overrides_max_prompt_size() {
+        // Arrange
+        let env = env_from(&[(MAX_PROMPT_SIZE_ENV, "20000")]);
+
+        // Act
+        let config = finish_loading(file_config(), "prompt.toml", None, env).unwrap();
+
+        // Assert
+        assert_eq!(config.max_prompt_size, 20000);
+        assert_eq!(config.prompt, "From file");
+    }
+
+    #[test]
+    fn test_env_overrides_model() {
+        // Arrange
+        let env = env_from(&[(MODEL_ENV, "haiku")]);
+
+        // Act
+        let config = finish_loading(file_config(), "prompt.toml", None, env).unwrap();
+
+        // Assert
+        assert_eq!(config.model.as_deref(), Some("haiku"));
+    }
+
+    #[test]
+    fn test_env_unset_or_empty_keeps_file_values() {
+        // Arrange
+        let env = env_from(&[(MODEL_ENV, "")]);
+
+        // Act
+        let config = finish_loading(file_config(), "prompt.toml", None, env).unwrap();
+
+        // Assert
+        assert_eq!(config, file_config());
+    }
+
+    #[test]
+    fn test_env_invalid_max_prompt_size() {
+        // Arrange / Act / Assert - not a number, and zero
+        for value in ["big", "0"] {
+            let env = env_from(&[(MAX_PROMPT_SIZE_ENV, value)]);
+            let result = finish_loading(file_config(), "prompt.toml", None, env);
+            assert!(
+                matches!(result, Err(Error::InvalidEnvVar { .. })),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_env_only_config_uses_defaults() {
+        // Arrange
+        let env = env_from(&[(PROMPT_ENV, "From env")]);
+
+        // Act
+        let config = finish_loading(Config::default(), ENV_CONFIG_SOURCE, None, env).unwrap();
+
+        // Assert
+        assert_eq!(config.prompt, "From env");
+        assert_eq!(config.max_prompt_size, 1_000_000);
+    }
+
+    #[test]
+    fn test_validate_config_accepts_defaults() {
+        // Arrange
+        let config = Config {
+            prompt: "Generate a message".to_string(),
+            ..Default::default()
+        };
+
+        // Act / Assert
+        assert!(validate_config(&config, "prompt.toml").is_ok());
+    }
 }
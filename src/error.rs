@@ -0,0 +1,261 @@
+//! Error types returned by the library
+//!
+//! Library functions return [`Error`] so callers can match on the failure
+//! reason (for example, truncating the diff on [`Error::PromptTooLarge`]).
+//! `Error` implements `std::error::Error`, so it converts into
+//! `anyhow::Error` with `?` in binaries.
+
+use std::path::PathBuf;
+
+/// Result type returned by the library's public functions
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the library's public functions
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The config file could not be read
+    #[error("Failed to read config file: {path}")]
+    ConfigRead {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The config file is not valid in its format
+    #[error("Failed to parse config file as {format}")]
+    ConfigParse {
+        /// `TOML`, `JSON`, `YAML`, or `merged config` for several files
+        format: &'static str,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// No config file exists in any of the searched locations
+    #[error(
+        "No configuration file found.\nSearched locations:\n{}\n\n\
+         Run 'claude_commit init' to create a config file.",
+        format_paths(searched)
+    )]
+    ConfigNotFound { searched: Vec<PathBuf> },
+
+    /// The prompt is empty or whitespace-only
+    #[error(
+        "Configuration error: 'prompt' field cannot be empty or whitespace-only. \
+         Please provide a valid prompt template in {path}"
+    )]
+    EmptyPrompt { path: String },
+
+    /// A config value is out of range or otherwise unusable
+    #[error("Configuration error: {message} in {path}")]
+    InvalidConfig { path: String, message: String },
+
+    /// The requested prompt profile does not exist
+    #[error(
+        "Profile '{name}' not found in {path}. Available profiles: {}",
+        format_profiles(available)
+    )]
+    ProfileNotFound {
+        name: String,
+        available: Vec<String>,
+        path: String,
+    },
+
+    /// A `redact_patterns` entry is not a valid regular expression
+    #[error("Invalid redact pattern '{pattern}'")]
+    InvalidPattern {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+
+    /// The prompt references an unset environment variable (with `strict_env`)
+    #[error("Environment variable '{name}' is not set{}", referenced_in(path))]
+    EnvVarNotSet {
+        name: String,
+        /// Config file whose prompt references the variable, when known
+        path: Option<String>,
+    },
+
+    /// A `CLAUDE_COMMIT_*` override has a value that cannot be used
+    #[error("Invalid value '{value}' for environment variable {name}")]
+    InvalidEnvVar { name: String, value: String },
+
+    /// The prompt exceeds the byte limit
+    #[error(
+        "Prompt size ({actual} bytes) exceeds maximum allowed size ({max} bytes). \
+         Consider reducing the size of staged changes or splitting into multiple commits."
+    )]
+    PromptTooLarge { actual: usize, max: usize },
+
+    /// The prompt exceeds the estimated token limit
+    #[error(
+        "Estimated prompt size (~{estimated} tokens) exceeds maximum allowed tokens ({max}). \
+         Consider reducing the size of staged changes or splitting into multiple commits."
+    )]
+    TooManyTokens { estimated: usize, max: usize },
+
+    /// The diff touches more files than `max_diff_files`
+    #[error(
+        "The diff changes {count} files, more than max_diff_files ({max}). \
+         Consider splitting the changes into multiple commits."
+    )]
+    TooManyFiles { count: usize, max: usize },
+
+    /// There are no changes to describe
+    #[error("{0}")]
+    NoChanges(String),
+
+    /// An empty commit range was given
+    #[error("Commit range must not be empty (e.g. main..HEAD)")]
+    EmptyRange,
+
+    /// A commit range that git would read as an option
+    #[error("Commit range must not start with '-': {0}")]
+    InvalidRange(String),
+
+    /// A git command exited with an error
+    #[error("{action}: {stderr}")]
+    GitFailed { action: String, stderr: String },
+
+    /// `git commit` exited with an error
+    #[error("Git commit command failed with exit code: {code:?}")]
+    CommitFailed { code: Option<i32> },
+
+    /// The pre-commit hook rejected the changes
+    #[error(
+        "Pre-commit hook failed with exit code: {code:?}\n\
+         Fix the issues reported by the pre-commit hook and try again."
+    )]
+    PreCommitFailed { code: Option<i32> },
+
+    /// `--amend` was requested in a repository without commits
+    #[error("Cannot amend: the repository has no commits yet")]
+    NoCommitToAmend,
+
+    /// Generating a message with Claude (or another backend) failed
+    #[error("{0:#}")]
+    ClaudeFailed(anyhow::Error),
+
+    /// The generated message does not follow Conventional Commits
+    #[error("{}", violations.join("\n"))]
+    LintFailed { violations: Vec<String> },
+
+    /// The subject line of the message is too long
+    #[error("subject is {length} characters long (maximum {max})")]
+    SubjectTooLong { length: usize, max: usize },
+
+    /// `--clipboard` was given but the binary was built without clipboard support
+    #[error(
+        "--clipboard is not supported by this build. \
+         Reinstall with `cargo install --path . --features clipboard` to enable it."
+    )]
+    ClipboardUnavailable,
+
+    /// Writing to the system clipboard failed
+    #[error("Failed to copy to clipboard: {0}")]
+    ClipboardFailed(String),
+
+    /// An output structure could not be serialized
+    #[error("Failed to serialize output as {format}")]
+    Serialize {
+        /// `JSON` or `TOML`
+        format: &'static str,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A file or process operation failed
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl Error {
+    /// Wrap an I/O error with a description of the failed operation
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+/// List searched config paths, one indented path per line
+fn format_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("  {}", path.display()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Name the config file for [`Error::EnvVarNotSet`], if known
+fn referenced_in(path: &Option<String>) -> String {
+    match path {
+        Some(path) => format!(" (referenced by the prompt in {})", path),
+        None => String::new(),
+    }
+}
+
+/// List available profile names, or `(none)`
+fn format_profiles(names: &[String]) -> String {
+    if names.is_empty() {
+        "(none)".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_too_large_is_matchable() {
+        // Arrange
+        let error = Error::PromptTooLarge {
+            actual: 120,
+            max: 100,
+        };
+
+        // Act / Assert - callers can branch on the variant and read the sizes
+        match &error {
+            Error::PromptTooLarge { actual, max } => assert!(actual > max),
+            other => panic!("unexpected error: {other}"),
+        }
+        assert!(error.to_string().contains("120 bytes"));
+    }
+
+    #[test]
+    fn test_io_error_keeps_source() {
+        // Arrange
+        let source = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+
+        // Act
+        let error = Error::io("Failed to read file", source);
+
+        // Assert
+        assert_eq!(error.to_string(), "Failed to read file");
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn test_converts_into_anyhow() {
+        // Arrange
+        fn fails() -> anyhow::Result<()> {
+            Err(Error::NoCommitToAmend)?
+        }
+
+        // Act
+        let error = fails().unwrap_err();
+
+        // Assert - the typed error can be recovered from anyhow
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::NoCommitToAmend)
+        ));
+    }
+}
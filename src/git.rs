@@ -1,7 +1,55 @@
-use anyhow::{Context, Result};
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::color;
+use crate::config::{Config, DiffAlgorithm};
+use crate::error::{Error, Result};
+use crate::log;
+use crate::redact::Redactor;
+use crate::runner::{CommandRunner, SystemRunner};
+
+/// Environment variable that overrides the git executable
+pub const GIT_BINARY_ENV: &str = "GIT_BINARY";
+
+/// Resolve the git executable from an optional override value
+///
+/// Falls back to `git` (looked up in PATH) when unset or empty.
+fn git_binary_from(value: Option<String>) -> String {
+    value
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "git".to_string())
+}
+
+/// Get the git executable, honoring the `GIT_BINARY` environment variable
+pub fn git_binary() -> String {
+    git_binary_from(std::env::var(GIT_BINARY_ENV).ok())
+}
+
+/// Create a `Command` for the configured git executable
+fn git_command() -> Command {
+    Command::new(git_binary())
+}
+
+/// Run git with `args` through `runner`, describing a launch failure with `context`
+fn run_git(runner: &dyn CommandRunner, args: &[String], context: &str) -> Result<Output> {
+    runner
+        .run(&git_binary(), args, None)
+        .map_err(|e| Error::io(context, e))
+}
+
+/// Owned copies of string arguments, as taken by [`CommandRunner::run`]
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Build an [`Error::GitFailed`] from the stderr of a failed git command
+fn git_failed(action: &str, output: &Output) -> Error {
+    Error::GitFailed {
+        action: action.to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }
+}
 
 /// Get the root directory of the current git repository
 ///
@@ -14,216 +62,2435 @@ use std::process::Command;
 /// * Not in a git repository
 /// * Git command fails
 pub fn get_git_root() -> Result<PathBuf> {
-    let output = Command::new("git")
+    let output = git_command()
         .args(["rev-parse", "--show-toplevel"])
         .output()
-        .context("Failed to execute git command")?;
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "Failed to get git root: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(git_failed("Failed to get git root", &output));
     }
 
     let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
     Ok(PathBuf::from(path))
 }
 
-/// Get git diff from the staging area
+/// Which set of changes to include in the diff
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffMode {
+    /// Staged changes only (`git diff --cached`)
+    #[default]
+    Staged,
+    /// Unstaged working tree changes (`git diff`)
+    Unstaged,
+    /// All staged and unstaged changes against HEAD (`git diff HEAD`)
+    All,
+}
+
+impl DiffMode {
+    /// Arguments passed to `git` for this mode
+    pub fn git_args(self) -> Vec<&'static str> {
+        match self {
+            DiffMode::Staged => vec!["diff", "--cached"],
+            DiffMode::Unstaged => vec!["diff"],
+            DiffMode::All => vec!["diff", "HEAD"],
+        }
+    }
+}
+
+/// Options controlling how the diff is collected
+///
+/// Use [`DiffOptions::from_config`] to pick up the diff-related settings
+/// from the configuration file, then adjust the CLI-driven fields.
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    /// Which set of changes to diff
+    pub mode: DiffMode,
+    /// Revision to compare against instead of the mode's default (e.g. `HEAD~1`)
+    pub revision: Option<String>,
+    /// Commit range to diff (e.g. `main..HEAD`); replaces `mode` and `revision`
+    pub range: Option<String>,
+    /// Paths to restrict the diff to (empty for the whole repository)
+    pub pathspecs: Vec<String>,
+    /// Patterns to leave out of the diff, following git's pathspec rules
+    pub exclude_paths: Vec<String>,
+    /// Replace binary file sections with a one-line summary
+    pub strip_binary: bool,
+    /// Label new files with [`NEW_FILE_MARKER`] (see [`mark_new_files`])
+    pub mark_new_files: bool,
+    /// Drop intent-to-add files (see [`skip_intent_to_add`])
+    pub skip_intent_to_add: bool,
+    /// Convert CRLF line endings to LF (see [`normalize_line_endings`])
+    pub normalize_eol: bool,
+    /// Keep at most this many hunk lines per file (see [`limit_lines_per_file`])
+    pub max_lines_per_file: Option<usize>,
+    /// Lines of context around each hunk (`-U<n>`); `None` uses git's default
+    pub context_lines: Option<usize>,
+    /// Line or word granularity (`--word-diff`)
+    pub diff_algorithm: DiffAlgorithm,
+    /// Mask secrets in the diff (see [`crate::redact`])
+    pub redact: bool,
+    /// Additional regexes to mask when `redact` is set
+    pub redact_patterns: Vec<String>,
+}
+
+impl DiffOptions {
+    /// Create diff options from the diff-related configuration fields
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            exclude_paths: config.exclude_paths.clone(),
+            strip_binary: config.strip_binary,
+            mark_new_files: config.mark_new_files,
+            skip_intent_to_add: config.skip_intent_to_add,
+            normalize_eol: config.normalize_eol,
+            max_lines_per_file: config.max_lines_per_file,
+            context_lines: Some(config.context_lines),
+            diff_algorithm: config.diff_algorithm,
+            redact: config.redact,
+            redact_patterns: config.redact_patterns.clone(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Repository-local file listing paths to leave out of the diff
+pub const IGNORE_FILE: &str = ".claude_commit_ignore";
+
+/// Read exclude patterns from `.claude_commit_ignore` at the repository root
+///
+/// The patterns complement the `exclude_paths` config and follow the same
+/// pathspec rules. A missing file yields no patterns.
+///
+/// # Errors
+///
+/// * Not in a git repository
+/// * The file exists but cannot be read
+pub fn get_ignore_patterns() -> Result<Vec<String>> {
+    let path = get_git_root()?.join(IGNORE_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| Error::io(format!("Failed to read {}", path.display()), e))?;
+    Ok(parse_ignore_file(&content))
+}
+
+/// Parse ignore file content: one pattern per line, `#` starts a comment line
+fn parse_ignore_file(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Build the argument vector for `git diff`
+///
+/// An explicit revision and options such as `-U<n>` come right after the
+/// mode arguments. A `range` replaces both the mode and the revision.
+/// Pathspecs and `:(exclude)` pathspecs are appended after a `--` separator
+/// so they are never interpreted as revisions or options. When neither is
+/// given, no separator is added.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::git::{DiffOptions, build_diff_args};
+///
+/// let options = DiffOptions {
+///     pathspecs: vec!["src/".to_string()],
+///     exclude_paths: vec!["Cargo.lock".to_string()],
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     build_diff_args(&options),
+///     vec!["diff", "--cached", "--", "src/", ":(exclude)Cargo.lock"]
+/// );
+/// ```
+pub fn build_diff_args(options: &DiffOptions) -> Vec<String> {
+    let mut args: Vec<String> = diff_command(options)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    if let Some(range) = &options.range {
+        args.push(range.clone());
+    } else if let Some(revision) = &options.revision {
+        args.push(revision.clone());
+    }
+    if let Some(lines) = options.context_lines {
+        args.push(format!("-U{}", lines));
+    }
+    if options.diff_algorithm == DiffAlgorithm::Word {
+        args.push("--word-diff".to_string());
+    }
+    if !options.pathspecs.is_empty() || !options.exclude_paths.is_empty() {
+        args.push("--".to_string());
+        args.extend(options.pathspecs.iter().cloned());
+        args.extend(
+            options
+                .exclude_paths
+                .iter()
+                .map(|pattern| format!(":(exclude){}", pattern)),
+        );
+    }
+    args
+}
+
+/// Get git diff according to the given options
+///
+/// With default options this executes `git diff --cached` to retrieve
+/// all staged changes. When `strip_binary` is set, binary file sections
+/// are collapsed by [`strip_binary_diffs`]; with `mark_new_files`, added
+/// files are labeled by [`mark_new_files`]; with `skip_intent_to_add`,
+/// intent-to-add files are dropped by [`skip_intent_to_add`].
+///
+/// # Arguments
 ///
-/// Executes `git diff --cached` to retrieve all staged changes.
+/// * `options` - Diff mode, pathspecs and exclusions
 ///
 /// # Returns
 ///
-/// * `Result<String>` - Output of git diff --cached
+/// * `Result<String>` - Trimmed output of git diff
 ///
 /// # Errors
 ///
 /// * Git command fails to execute
 /// * Not in a git repository
+/// * `DiffMode::All` in a repository without any commits (no HEAD)
 ///
 /// # Example
 ///
 /// ```no_run
-/// use claude_commit::git::get_git_diff;
+/// use claude_commit::git::{DiffOptions, get_git_diff};
 ///
 /// # fn main() -> anyhow::Result<()> {
-/// let diff = get_git_diff()?;
+/// let diff = get_git_diff(&DiffOptions::default())?;
 /// println!("Staged changes:\n{}", diff);
 /// # Ok(())
 /// # }
 /// ```
-pub fn get_git_diff() -> Result<String> {
-    let output = Command::new("git")
-        .args(["diff", "--cached"])
+pub fn get_git_diff(options: &DiffOptions) -> Result<String> {
+    get_git_diff_with(&SystemRunner, options)
+}
+
+/// [`get_git_diff`] running git through `runner`
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_git_diff_with(runner: &dyn CommandRunner, options: &DiffOptions) -> Result<String> {
+    if let Some(range) = &options.range {
+        validate_range(range)?;
+    }
+    let output = run_git(
+        runner,
+        &build_diff_args(options),
+        "Failed to execute git command. Make sure git is installed and in PATH",
+    )?;
+
+    if !output.status.success() {
+        return Err(git_failed("Git diff command failed", &output));
+    }
+
+    let mut diff = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if options.normalize_eol {
+        diff = normalize_line_endings(&diff);
+    }
+    if options.skip_intent_to_add {
+        diff = skip_intent_to_add(&diff);
+    }
+    if options.strip_binary {
+        diff = strip_binary_diffs(&diff);
+    }
+    if options.mark_new_files {
+        diff = mark_new_files(&diff);
+    }
+    if let Some(max_lines) = options.max_lines_per_file {
+        diff = limit_lines_per_file(&diff, max_lines);
+    }
+    if options.redact {
+        diff = Redactor::new(&options.redact_patterns)?.redact(&diff);
+    }
+    Ok(diff)
+}
+
+/// Build the argument vector for the staged diff of a single file
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::git::build_file_diff_args;
+///
+/// assert_eq!(
+///     build_file_diff_args("src/main.rs"),
+///     vec!["diff", "--cached", "--", "src/main.rs"]
+/// );
+/// ```
+pub fn build_file_diff_args(path: &str) -> Vec<String> {
+    ["diff", "--cached", "--", path]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// Get the staged diff of a single file (`git diff --cached -- <path>`)
+///
+/// Unlike [`get_git_diff`], no options are applied: the raw, trimmed diff
+/// of that one file is returned.
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+/// * The file has no staged changes
+pub fn get_file_diff(path: &str) -> Result<String> {
+    let output = git_command()
+        .args(build_file_diff_args(path))
         .output()
-        .context("Failed to execute git command. Make sure git is installed and in PATH")?;
+        .map_err(|e| {
+            Error::io(
+                "Failed to execute git command. Make sure git is installed and in PATH",
+                e,
+            )
+        })?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "Git diff command failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(git_failed("Git diff command failed", &output));
     }
 
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    let diff = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if diff.is_empty() {
+        return Err(Error::NoChanges(format!(
+            "No staged changes found for {}. Stage it with `git add {}` first.",
+            path, path
+        )));
+    }
+    Ok(diff)
 }
 
-/// Write the commit message to .git/COMMIT_MSG_GENERATED
+/// Split a unified diff into per-file sections
 ///
-/// This creates a temporary file in the git directory that will be
-/// used as the default message when launching the git commit editor.
+/// Each section starts at a `diff --git` header line and runs up to (but
+/// not including) the next one. Any text before the first header is
+/// returned as its own section. Concatenating the sections yields the
+/// original diff unchanged.
+pub fn split_diff_by_file(diff: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git ") && offset > start {
+            sections.push(&diff[start..offset]);
+            start = offset;
+        }
+        offset += line.len();
+    }
+    if offset > start {
+        sections.push(&diff[start..offset]);
+    }
+
+    sections
+}
+
+/// Extract the file path from a `Binary files ... differ` line in a file section
 ///
-/// # Arguments
+/// Prefers the new path, falling back to the old one for deletions.
+fn binary_file_path(section: &str) -> Option<String> {
+    let line = section
+        .lines()
+        .find(|line| line.starts_with("Binary files ") && line.ends_with(" differ"))?;
+    let paths = line
+        .strip_prefix("Binary files ")?
+        .strip_suffix(" differ")?;
+    let (old, new) = paths.split_once(" and ")?;
+    let path = if new == "/dev/null" {
+        old.strip_prefix("a/").unwrap_or(old)
+    } else {
+        new.strip_prefix("b/").unwrap_or(new)
+    };
+    Some(path.to_string())
+}
+
+/// Replace binary file sections of a diff with a short summary line
 ///
-/// * `message` - Generated commit message content
+/// Each file whose section contains a `Binary files ... differ` line is
+/// replaced by `(binary file changed: <path>)`. Text file sections are
+/// passed through untouched.
 ///
-/// # Returns
+/// # Example
 ///
-/// * `Result<String>` - Path to the written file
+/// ```
+/// use claude_commit::git::strip_binary_diffs;
 ///
-/// # Errors
+/// let diff = "diff --git a/logo.png b/logo.png\n\
+///             index 1111111..2222222 100644\n\
+///             Binary files a/logo.png and b/logo.png differ";
+/// assert_eq!(strip_binary_diffs(diff), "(binary file changed: logo.png)");
+/// ```
+pub fn strip_binary_diffs(diff: &str) -> String {
+    let mut result = String::with_capacity(diff.len());
+
+    for section in split_diff_by_file(diff) {
+        match binary_file_path(section) {
+            Some(path) => {
+                result.push_str(&format!("(binary file changed: {})", path));
+                if section.ends_with('\n') {
+                    result.push('\n');
+                }
+            }
+            None => result.push_str(section),
+        }
+    }
+
+    result
+}
+
+/// Convert CRLF line endings to LF
 ///
-/// * .git directory does not exist (not a git repository)
-/// * Failed to write file (permission issues)
+/// Lone `\r` and `\n` characters are left as they are.
 ///
 /// # Example
 ///
-/// ```no_run
-/// use claude_commit::git::write_commit_message;
+/// ```
+/// use claude_commit::git::normalize_line_endings;
 ///
-/// # fn main() -> anyhow::Result<()> {
-/// let message = "feat: add new feature\n\nDetailed description here.";
-/// let path = write_commit_message(message)?;
-/// println!("Message written to: {}", path);
-/// # Ok(())
-/// # }
+/// assert_eq!(normalize_line_endings("+a\r\n+b\n"), "+a\n+b\n");
 /// ```
-pub fn write_commit_message(message: &str) -> Result<String> {
-    let commit_msg_path = ".git/COMMIT_MSG_GENERATED";
-    fs::write(commit_msg_path, message).context(
-        "Failed to write to .git/COMMIT_MSG_GENERATED. Make sure you are in a git repository.",
-    )?;
-    Ok(commit_msg_path.to_string())
+pub fn normalize_line_endings(diff: &str) -> String {
+    diff.replace("\r\n", "\n")
 }
 
-/// Execute git commit -v -e -F to launch an editor
+/// Drop the sections of intent-to-add files (`git add -N`)
 ///
-/// This function executes the git commit command with the generated message,
-/// allowing the user to review and edit it in their configured editor.
+/// In a staged diff such a file is a `new file mode` header with no hunk.
+/// Sections that add a file without a hunk or binary content are removed.
+/// The diff alone cannot tell such a file from a staged empty file
+/// (`__init__.py`, `.gitkeep`), so those are removed as well.
 ///
-/// # Arguments
+/// # Example
 ///
-/// * `msg_file` - Path to the commit message file
+/// ```
+/// use claude_commit::git::skip_intent_to_add;
 ///
-/// # Returns
+/// let diff = "diff --git a/a.rs b/a.rs\n\
+///             new file mode 100644\n\
+///             index 0000000..e69de29\n\
+///             diff --git a/b.rs b/b.rs\n\
+///             @@ -1 +1 @@\n\
+///             +b";
+/// assert_eq!(skip_intent_to_add(diff), "diff --git a/b.rs b/b.rs\n@@ -1 +1 @@\n+b");
+/// ```
+pub fn skip_intent_to_add(diff: &str) -> String {
+    split_diff_by_file(diff)
+        .into_iter()
+        .filter(|section| !is_empty_new_file(section))
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Whether a file section adds a file without any content
+fn is_empty_new_file(section: &str) -> bool {
+    section.starts_with("diff --git ")
+        && section
+            .lines()
+            .any(|line| line.starts_with("new file mode "))
+        && !section.lines().any(|line| {
+            line.starts_with("@@")
+                || line.starts_with("Binary files ")
+                || line == "GIT binary patch"
+        })
+}
+
+/// Line inserted after the `diff --git` header of a newly added file
+pub const NEW_FILE_MARKER: &str = "[NEW FILE]";
+
+/// Label the sections of newly added files with [`NEW_FILE_MARKER`]
 ///
-/// * `Result<()>` - Ok if commit succeeds, Err otherwise
+/// A section is new when it has a `new file mode` line; the marker goes
+/// right after its `diff --git` header so it stays within the file's
+/// section. Other sections are passed through untouched.
 ///
-/// # Errors
+/// # Example
 ///
-/// * Failed to execute git command
-/// * Git not found in PATH
-/// * User aborted the commit
-/// * Commit validation failed
+/// ```
+/// use claude_commit::git::mark_new_files;
+///
+/// let diff = "diff --git a/a.rs b/a.rs\n\
+///             new file mode 100644\n\
+///             +fn main() {}";
+/// assert_eq!(
+///     mark_new_files(diff),
+///     "diff --git a/a.rs b/a.rs\n[NEW FILE]\nnew file mode 100644\n+fn main() {}"
+/// );
+/// ```
+pub fn mark_new_files(diff: &str) -> String {
+    let mut result = String::with_capacity(diff.len());
+
+    for section in split_diff_by_file(diff) {
+        let is_new = section
+            .lines()
+            .any(|line| line.starts_with("new file mode "));
+        match section.split_once('\n') {
+            Some((header, rest)) if is_new && header.starts_with("diff --git ") => {
+                result.push_str(header);
+                result.push('\n');
+                result.push_str(NEW_FILE_MARKER);
+                result.push('\n');
+                result.push_str(rest);
+            }
+            _ => result.push_str(section),
+        }
+    }
+
+    result
+}
+
+/// Truncate each file's hunks to at most `max_lines` lines
+///
+/// Lines are counted from the first `@@` hunk header of a file, so the
+/// `diff --git` header block is always kept. Whole hunks are kept while they
+/// fit; only when the first hunk alone is too long is it cut mid-hunk. The
+/// dropped lines are replaced by `... [N more lines omitted]`. Sections
+/// without hunks (e.g. binary or renamed files) are passed through untouched.
 ///
 /// # Example
 ///
-/// ```no_run
-/// use claude_commit::git::run_git_commit;
+/// ```
+/// use claude_commit::git::limit_lines_per_file;
 ///
-/// # fn main() -> anyhow::Result<()> {
-/// let msg_file = ".git/COMMIT_MSG_GENERATED";
-/// run_git_commit(msg_file)?;
-/// println!("Commit successful!");
-/// # Ok(())
-/// # }
+/// let diff = "diff --git a/a.rs b/a.rs\n\
+///             @@ -1 +1 @@\n\
+///             +one\n\
+///             @@ -10 +10 @@\n\
+///             +two";
+/// assert_eq!(
+///     limit_lines_per_file(diff, 3),
+///     "diff --git a/a.rs b/a.rs\n@@ -1 +1 @@\n+one\n... [2 more lines omitted]"
+/// );
 /// ```
-pub fn run_git_commit(msg_file: &str) -> Result<()> {
-    let status = Command::new("git")
-        .args(["commit", "-v", "-e", "-F", msg_file])
-        .status()
-        .context("Failed to execute git commit command")?;
+pub fn limit_lines_per_file(diff: &str, max_lines: usize) -> String {
+    let mut result = String::with_capacity(diff.len());
 
-    if !status.success() {
-        anyhow::bail!(
-            "Git commit command failed with exit code: {:?}",
-            status.code()
-        );
+    for section in split_diff_by_file(diff) {
+        let mut header = String::new();
+        let mut hunks: Vec<Vec<&str>> = Vec::new();
+        for line in section.split_inclusive('\n') {
+            if line.starts_with("@@") {
+                hunks.push(Vec::new());
+            }
+            match hunks.last_mut() {
+                Some(hunk) => hunk.push(line),
+                None => header.push_str(line),
+            }
+        }
+
+        result.push_str(&header);
+        let mut kept = 0;
+        let mut omitted = 0;
+        for hunk in &hunks {
+            if omitted > 0 || kept + hunk.len() > max_lines {
+                let take = if kept == 0 { max_lines } else { 0 };
+                hunk[..take].iter().for_each(|line| result.push_str(line));
+                kept += take;
+                omitted += hunk.len() - take;
+            } else {
+                hunk.iter().for_each(|line| result.push_str(line));
+                kept += hunk.len();
+            }
+        }
+
+        if omitted > 0 {
+            if !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result.push_str(&format!("... [{} more lines omitted]", omitted));
+            if section.ends_with('\n') {
+                result.push('\n');
+            }
+        }
     }
 
-    Ok(())
+    result
 }
 
-/// Execute git commit without opening an editor
+/// Whether a line is a merge-conflict marker left by git
 ///
-/// Commits directly using the generated message file without
-/// prompting the user to review in an editor.
+/// `<<<<<<<` and `>>>>>>>` may be followed by a label; `=======` must be the
+/// whole line so that longer rules (e.g. Markdown headings) do not match.
+fn is_conflict_marker(line: &str) -> bool {
+    let labeled = |marker: &str| {
+        line.strip_prefix(marker)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+    };
+    labeled("<<<<<<<") || labeled(">>>>>>>") || line == "======="
+}
+
+/// List the files whose added lines contain merge-conflict markers
 ///
-/// # Arguments
+/// Only `+` lines are checked, so removing leftover markers is not reported.
+/// Each file is listed once, by its new path; text before the first
+/// `diff --git` header is reported as `(diff)`.
 ///
-/// * `msg_file` - Path to the commit message file
+/// # Example
 ///
-/// # Returns
+/// ```
+/// use claude_commit::git::find_conflict_markers;
 ///
-/// * `Result<()>` - Ok if commit succeeds, Err otherwise
+/// let diff = "diff --git a/a.rs b/a.rs\n\
+///             +<<<<<<< HEAD\n\
+///             +let x = 1;\n\
+///             +=======\n\
+///             +let x = 2;\n\
+///             +>>>>>>> feature";
+/// assert_eq!(find_conflict_markers(diff), vec!["a.rs"]);
+/// ```
+pub fn find_conflict_markers(diff: &str) -> Vec<String> {
+    split_diff_by_file(diff)
+        .into_iter()
+        .filter(|section| {
+            section.lines().any(|line| {
+                !line.starts_with("+++") && line.strip_prefix('+').is_some_and(is_conflict_marker)
+            })
+        })
+        .map(|section| {
+            section
+                .lines()
+                .next()
+                .and_then(|header| header.strip_prefix("diff --git "))
+                .and_then(|paths| paths.rsplit_once(" b/"))
+                .map_or_else(|| "(diff)".to_string(), |(_, path)| path.to_string())
+        })
+        .collect()
+}
+
+/// Get the `--stat` summary for the diff described by the given options
+///
+/// Uses the same mode, pathspecs and exclusions as [`get_git_diff`] so the
+/// summary always matches the diff it accompanies.
 ///
 /// # Errors
 ///
-/// * Failed to execute git command
-/// * Commit validation failed (e.g. commit-msg hook)
-pub fn run_git_commit_direct(msg_file: &str) -> Result<()> {
-    let status = Command::new("git")
-        .args(["commit", "-F", msg_file])
-        .status()
-        .context("Failed to execute git commit command")?;
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_diff_stat(options: &DiffOptions) -> Result<String> {
+    get_diff_stat_with(&SystemRunner, options)
+}
 
-    if !status.success() {
-        anyhow::bail!(
-            "Git commit command failed with exit code: {:?}",
-            status.code()
-        );
+/// [`get_diff_stat`] running git through `runner`
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_diff_stat_with(runner: &dyn CommandRunner, options: &DiffOptions) -> Result<String> {
+    if let Some(range) = &options.range {
+        validate_range(range)?;
+    }
+    let output = run_git(
+        runner,
+        &build_diff_stat_args(options),
+        "Failed to execute git command. Make sure git is installed and in PATH",
+    )?;
+
+    if !output.status.success() {
+        return Err(git_failed("Git diff --stat command failed", &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// Build the argument vector for `git diff --stat`
+fn build_diff_stat_args(options: &DiffOptions) -> Vec<String> {
+    let mut args = build_diff_args(options);
+    args.insert(diff_command(options).len(), "--stat".to_string());
+    args
+}
+
+/// The `git diff` subcommand and mode flags, before any revision or option
+fn diff_command(options: &DiffOptions) -> Vec<&'static str> {
+    if options.range.is_some() {
+        vec!["diff"]
+    } else {
+        options.mode.git_args()
     }
+}
 
+/// Check a commit range such as `main..HEAD` before it is passed to git
+///
+/// A range starting with `-` would be read by git as an option, so it is
+/// refused along with an empty one.
+///
+/// # Errors
+///
+/// * `range` is empty or starts with `-`
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::git::validate_range;
+///
+/// assert!(validate_range("main..HEAD").is_ok());
+/// assert!(validate_range("--output=/tmp/x").is_err());
+/// ```
+pub fn validate_range(range: &str) -> Result<()> {
+    if range.trim().is_empty() {
+        return Err(Error::EmptyRange);
+    }
+    if range.starts_with('-') {
+        return Err(Error::InvalidRange(range.to_string()));
+    }
     Ok(())
 }
 
-/// Run the pre-commit hook if it exists
+/// Get git diff for the given [`DiffMode`] and pathspecs
 ///
-/// Executes `.git/hooks/pre-commit` before Claude generates a commit message.
-/// This catches linter/formatter errors early, avoiding unnecessary API calls.
-/// If the hook does not exist, silently succeeds.
+/// Shorthand for [`get_git_diff`] with no exclusions.
+pub fn get_git_diff_with_mode(mode: DiffMode, pathspecs: &[String]) -> Result<String> {
+    get_git_diff(&DiffOptions {
+        mode,
+        pathspecs: pathspecs.to_vec(),
+        ..DiffOptions::default()
+    })
+}
+
+/// Stage all changes in the working tree (`git add -A`)
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Ok if hook succeeds or does not exist, Err if hook fails
+/// * `Result<usize>` - Number of files newly staged (added, modified or removed)
 ///
 /// # Errors
 ///
-/// * Hook script fails to execute
-/// * Hook exits with non-zero status
-pub fn run_pre_commit_hook() -> Result<()> {
-    let hook_path = PathBuf::from(".git/hooks/pre-commit");
+/// * Git command fails to execute
+/// * `git add` exits with an error (e.g. index lock held by another process)
+pub fn stage_all() -> Result<usize> {
+    let output = git_command()
+        .args(["add", "-A", "--verbose"])
+        .output()
+        .map_err(|e| Error::io("Failed to execute git add", e))?;
 
-    if !hook_path.exists() {
-        return Ok(());
+    if !output.status.success() {
+        return Err(git_failed(
+            "Failed to stage changes with git add -A",
+            &output,
+        ));
     }
 
-    eprintln!("Running pre-commit hook...");
+    Ok(count_staged(&String::from_utf8_lossy(&output.stdout)))
+}
 
-    let status = Command::new(&hook_path)
-        .status()
-        .context("Failed to execute pre-commit hook")?;
+/// Count the `add '<path>'` / `remove '<path>'` lines of `git add --verbose`
+fn count_staged(stdout: &str) -> usize {
+    stdout
+        .lines()
+        .filter(|line| line.starts_with("add '") || line.starts_with("remove '"))
+        .count()
+}
 
-    if !status.success() {
-        anyhow::bail!(
-            "Pre-commit hook failed with exit code: {:?}\n\
-             Fix the issues reported by the pre-commit hook and try again.",
-            status.code()
-        );
+/// Get the list of files with staged changes
+///
+/// Runs `git diff --cached --name-only`.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - Paths relative to the repository root (empty if nothing is staged)
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_changed_files() -> Result<Vec<String>> {
+    let output = git_command()
+        .args(["diff", "--cached", "--name-only"])
+        .output()
+        .map_err(|e| {
+            Error::io(
+                "Failed to execute git command. Make sure git is installed and in PATH",
+                e,
+            )
+        })?;
+
+    if !output.status.success() {
+        return Err(git_failed("Git diff command failed", &output));
     }
 
-    eprintln!("Pre-commit hook passed.");
-    Ok(())
+    Ok(parse_name_only(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Split `--name-only` output into file paths, skipping blank lines
+fn parse_name_only(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Get the subjects of the most recent commits, newest first
+///
+/// Runs `git log -n <n> --format=%s`. A repository without commits yields
+/// an empty list instead of an error.
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_recent_commit_subjects(n: usize) -> Result<Vec<String>> {
+    get_recent_commit_subjects_with(&SystemRunner, n)
+}
+
+/// [`get_recent_commit_subjects`] running git through `runner`
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * `git log` exits with an error
+pub fn get_recent_commit_subjects_with(
+    runner: &dyn CommandRunner,
+    n: usize,
+) -> Result<Vec<String>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    let head = run_git(
+        runner,
+        &to_args(&["rev-parse", "--verify", "--quiet", "HEAD"]),
+        "Failed to execute git command",
+    )?;
+    if !head.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let output = run_git(
+        runner,
+        &to_args(&["log", "-n", &n.to_string(), "--format=%s"]),
+        "Failed to execute git log",
+    )?;
+
+    if !output.status.success() {
+        return Err(git_failed("Failed to get recent commits", &output));
+    }
+
+    Ok(parse_name_only(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Get the name of the current branch
+///
+/// Runs `git rev-parse --abbrev-ref HEAD`. On a detached HEAD, where git
+/// prints `HEAD`, the short commit hash is returned instead. Before the first
+/// commit, when `rev-parse` fails, the branch HEAD points to is read with
+/// `git symbolic-ref --short HEAD`.
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_current_branch() -> Result<String> {
+    get_current_branch_with(&SystemRunner)
+}
+
+/// [`get_current_branch`] running git through `runner`
+///
+/// # Errors
+///
+/// * Git command fails to execute
+/// * Not in a git repository
+pub fn get_current_branch_with(runner: &dyn CommandRunner) -> Result<String> {
+    let output = run_git(
+        runner,
+        &to_args(&["rev-parse", "--abbrev-ref", "HEAD"]),
+        "Failed to execute git command",
+    )?;
+    if !output.status.success() {
+        // An unborn branch has no commit for rev-parse, but HEAD still names it
+        let unborn = run_git(
+            runner,
+            &to_args(&["symbolic-ref", "--short", "HEAD"]),
+            "Failed to execute git command",
+        )?;
+        if unborn.status.success() {
+            return Ok(String::from_utf8_lossy(&unborn.stdout).trim().to_string());
+        }
+        return Err(git_failed("Failed to get current branch", &output));
+    }
+
+    branch_or_short_sha(&String::from_utf8_lossy(&output.stdout), || {
+        let output = run_git(
+            runner,
+            &to_args(&["rev-parse", "--short", "HEAD"]),
+            "Failed to execute git command",
+        )?;
+        if !output.status.success() {
+            return Err(git_failed("Failed to get HEAD commit", &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    })
+}
+
+/// Turn `rev-parse --abbrev-ref HEAD` output into a branch name, using `short_sha` when detached
+fn branch_or_short_sha(
+    abbrev_ref: &str,
+    short_sha: impl FnOnce() -> Result<String>,
+) -> Result<String> {
+    match abbrev_ref.trim() {
+        "HEAD" => short_sha(),
+        branch => Ok(branch.to_string()),
+    }
+}
+
+/// Get the git directory of the current repository
+///
+/// Runs `git rev-parse --git-dir`, which resolves the real directory for
+/// worktrees and submodules where `.git` is a file pointing elsewhere.
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the git directory (may be relative to the current directory)
+///
+/// # Errors
+///
+/// * Not in a git repository
+/// * Git command fails
+pub fn get_git_dir() -> Result<PathBuf> {
+    let output = git_command()
+        .args(["rev-parse", "--git-dir"])
+        .output()
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
+
+    if !output.status.success() {
+        return Err(git_failed("Failed to get git directory", &output));
+    }
+
+    Ok(git_dir_from_output(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Convert `git rev-parse --git-dir` output into a path, defaulting to `.git`
+fn git_dir_from_output(stdout: &str) -> PathBuf {
+    let dir = stdout.trim();
+    if dir.is_empty() {
+        PathBuf::from(".git")
+    } else {
+        PathBuf::from(dir)
+    }
+}
+
+/// Detect an unfinished merge or rebase in the current repository
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - `"merge"` or `"rebase"`, or `None` when the
+///   repository is in a normal state
+///
+/// # Errors
+///
+/// * Not in a git repository
+/// * Git command fails
+pub fn detect_special_state() -> Result<Option<String>> {
+    Ok(special_state_in(&get_git_dir()?))
+}
+
+/// Label the operation left in progress in `git_dir`, if any
+///
+/// `MERGE_HEAD` marks a merge; the `rebase-merge` and `rebase-apply`
+/// directories mark a rebase (`rebase-apply` is also used by `git am`).
+fn special_state_in(git_dir: &Path) -> Option<String> {
+    if git_dir.join("MERGE_HEAD").exists() {
+        Some("merge".to_string())
+    } else if git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply").is_dir() {
+        Some("rebase".to_string())
+    } else {
+        None
+    }
+}
+
+/// Get the directory git runs hooks from
+///
+/// Resolved with `git rev-parse --git-path hooks`, so worktrees share the
+/// main repository's hooks and `core.hooksPath` is honored.
+///
+/// # Errors
+///
+/// * Failed to execute git command
+/// * Not in a git repository
+pub fn get_hooks_dir() -> Result<PathBuf> {
+    hooks_dir_in(None)
+}
+
+/// [`get_hooks_dir`] for the repository containing `dir`, or the current
+/// directory when `None`
+///
+/// git prints the path relative to where it runs, so it is joined onto `dir`.
+fn hooks_dir_in(dir: Option<&Path>) -> Result<PathBuf> {
+    let mut command = git_command();
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let output = command
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
+
+    if !output.status.success() {
+        return Err(git_failed("Failed to get git hooks directory", &output));
+    }
+
+    let hooks = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Ok(match dir {
+        Some(dir) => dir.join(hooks),
+        None => hooks,
+    })
+}
+
+/// Where [`write_commit_message`] writes: `path` when given, otherwise
+/// `<git dir>/COMMIT_MSG_GENERATED`
+///
+/// # Errors
+///
+/// * Not a git repository (when no override path is given)
+pub fn generated_message_path(path: Option<&str>) -> Result<PathBuf> {
+    match path {
+        Some(p) => Ok(PathBuf::from(p)),
+        None => Ok(get_git_dir()?.join("COMMIT_MSG_GENERATED")),
+    }
+}
+
+/// Write the commit message to `<git dir>/COMMIT_MSG_GENERATED`
+///
+/// This creates a temporary file in the git directory that will be
+/// used as the default message when launching the git commit editor.
+/// The git directory is resolved with [`get_git_dir`], so worktrees and
+/// submodules are supported.
+///
+/// # Arguments
+///
+/// * `message` - Generated commit message content
+/// * `path` - Optional file to write instead of the default location
+///
+/// # Returns
+///
+/// * `Result<String>` - Path to the written file
+///
+/// # Errors
+///
+/// * Not a git repository (when no override path is given)
+/// * Failed to write file (permission issues)
+///
+/// # Example
+///
+/// ```no_run
+/// use claude_commit::git::write_commit_message;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let message = "feat: add new feature\n\nDetailed description here.";
+/// let path = write_commit_message(message, None)?;
+/// println!("Message written to: {}", path);
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_commit_message(message: &str, path: Option<&str>) -> Result<String> {
+    let commit_msg_path = generated_message_path(path)?;
+    fs::write(&commit_msg_path, message).map_err(|e| {
+        Error::io(
+            format!(
+                "Failed to write commit message to {}",
+                commit_msg_path.display()
+            ),
+            e,
+        )
+    })?;
+    Ok(commit_msg_path.to_string_lossy().to_string())
+}
+
+/// Copy the generated message into `<git dir>/COMMIT_EDITMSG`
+///
+/// `git commit -F` rewrites `COMMIT_EDITMSG` from the `-F` file itself, so
+/// this only matters for tools that read the file before git does (for
+/// example, editor integrations or hook chains started outside `git commit`).
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the written `COMMIT_EDITMSG`
+///
+/// # Errors
+///
+/// * Not a git repository
+/// * Failed to read `msg_file` or write `COMMIT_EDITMSG`
+pub fn write_editmsg(msg_file: &str) -> Result<PathBuf> {
+    let message = fs::read_to_string(msg_file)
+        .map_err(|e| Error::io(format!("Failed to read {}", msg_file), e))?;
+    let path = get_git_dir()?.join("COMMIT_EDITMSG");
+    fs::write(&path, message)
+        .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))?;
+    Ok(path)
+}
+
+/// Line below which `git commit -v` puts the diff in `COMMIT_EDITMSG`
+const SCISSORS_LINE: &str = "# ------------------------ >8 ------------------------";
+
+/// Read the message left in `COMMIT_EDITMSG` by the last commit or edit
+///
+/// `#` comment lines are removed, along with everything below the
+/// `git commit -v` scissors line.
+///
+/// # Returns
+///
+/// * `Option<String>` - The message, or `None` if the file is missing,
+///   unreadable, or contains only comments and blank lines
+pub fn read_commit_editmsg(git_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(git_dir.join("COMMIT_EDITMSG")).ok()?;
+    let message = content
+        .lines()
+        .take_while(|line| *line != SCISSORS_LINE)
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = message.trim();
+    (!message.is_empty()).then(|| message.to_string())
+}
+
+/// Options controlling how `git commit` is invoked
+#[derive(Clone, Debug, Default)]
+pub struct CommitOptions {
+    /// Replace the last commit (`--amend`) instead of creating a new one
+    pub amend: bool,
+    /// Also copy the message file to `COMMIT_EDITMSG` before committing
+    pub write_editmsg: bool,
+    /// GPG-sign the commit (`-S`); requires git's signing key to be configured
+    pub sign: bool,
+    /// Skip the pre-commit and commit-msg hooks (`--no-verify`)
+    ///
+    /// Checks such as formatters, linters and secret scanners configured as
+    /// hooks do not run, so the commit may contain what they would reject.
+    pub no_verify: bool,
+    /// Commit only these paths (`-- <paths>`), leaving other staged changes staged
+    ///
+    /// As with `git commit <paths>`, the working tree content of the paths
+    /// is committed, including changes that are not staged.
+    pub only: Vec<String>,
+}
+
+/// Build the argument vector for `git commit`
+///
+/// # Arguments
+///
+/// * `msg_file` - Path to the commit message file (passed with `-F`)
+/// * `edit` - Open the editor with the verbose diff (`-v -e`)
+/// * `options` - Additional commit options
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::git::{CommitOptions, build_commit_args};
+///
+/// let args = build_commit_args("msg.txt", true, &CommitOptions::default());
+/// assert_eq!(args, vec!["commit", "-v", "-e", "-F", "msg.txt"]);
+/// ```
+pub fn build_commit_args(msg_file: &str, edit: bool, options: &CommitOptions) -> Vec<String> {
+    let mut args = vec!["commit".to_string()];
+    if options.amend {
+        args.push("--amend".to_string());
+    }
+    if options.sign {
+        args.push("-S".to_string());
+    }
+    if options.no_verify {
+        args.push("--no-verify".to_string());
+    }
+    if edit {
+        args.push("-v".to_string());
+        args.push("-e".to_string());
+    }
+    args.push("-F".to_string());
+    args.push(msg_file.to_string());
+    if !options.only.is_empty() {
+        args.push("--".to_string());
+        args.extend(options.only.iter().cloned());
+    }
+    args
+}
+
+/// Run `git commit` with `msg_file`, failing on a non-zero exit code
+fn run_commit_command(msg_file: &str, edit: bool, options: &CommitOptions) -> Result<()> {
+    if options.write_editmsg {
+        write_editmsg(msg_file)?;
+    }
+
+    let status = git_command()
+        .args(build_commit_args(msg_file, edit, options))
+        .status()
+        .map_err(|e| Error::io("Failed to execute git commit command", e))?;
+
+    if !status.success() {
+        return Err(Error::CommitFailed {
+            code: status.code(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Execute git commit -v -e -F to launch an editor
+///
+/// This function executes the git commit command with the generated message,
+/// allowing the user to review and edit it in their configured editor.
+///
+/// # Arguments
+///
+/// * `msg_file` - Path to the commit message file
+/// * `options` - Additional commit options (e.g. amend)
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if commit succeeds, Err otherwise
+///
+/// # Errors
+///
+/// * Failed to execute git command
+/// * Git not found in PATH
+/// * User aborted the commit
+/// * Commit validation failed
+///
+/// # Example
+///
+/// ```no_run
+/// use claude_commit::git::{CommitOptions, run_git_commit};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let msg_file = ".git/COMMIT_MSG_GENERATED";
+/// run_git_commit(msg_file, &CommitOptions::default())?;
+/// println!("Commit successful!");
+/// # Ok(())
+/// # }
+/// ```
+pub fn run_git_commit(msg_file: &str, options: &CommitOptions) -> Result<()> {
+    run_commit_command(msg_file, true, options)
+}
+
+/// Execute git commit without opening an editor
+///
+/// Commits directly using the generated message file without
+/// prompting the user to review in an editor.
+///
+/// # Arguments
+///
+/// * `msg_file` - Path to the commit message file
+/// * `options` - Additional commit options (e.g. amend)
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if commit succeeds, Err otherwise
+///
+/// # Errors
+///
+/// * Failed to execute git command
+/// * Commit validation failed (e.g. commit-msg hook)
+pub fn run_git_commit_direct(msg_file: &str, options: &CommitOptions) -> Result<()> {
+    run_commit_command(msg_file, false, options)
+}
+
+/// Rewrite the last commit's message with `git commit --amend -F`
+///
+/// Staged changes are folded into the amended commit. This rewrites
+/// history: do not amend commits that have already been pushed to a
+/// shared branch.
+///
+/// `options.amend` is implied; other options are honored.
+///
+/// # Errors
+///
+/// * Failed to execute git command
+/// * No commit to amend
+/// * Commit validation failed (e.g. commit-msg hook)
+pub fn run_git_amend(msg_file: &str, options: &CommitOptions) -> Result<()> {
+    let mut options = options.clone();
+    options.amend = true;
+    run_git_commit_direct(msg_file, &options)
+}
+
+/// Build the argument vector for `git commit --amend --no-edit`
+///
+/// `options.amend` is implied. `options.sign`, `options.no_verify` and
+/// `options.only` are honored; `options.write_editmsg` does not apply
+/// since the message is not replaced.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::git::{CommitOptions, build_amend_keep_message_args};
+///
+/// let args = build_amend_keep_message_args(&CommitOptions::default());
+/// assert_eq!(args, vec!["commit", "--amend", "--no-edit"]);
+/// ```
+pub fn build_amend_keep_message_args(options: &CommitOptions) -> Vec<String> {
+    let mut args = vec![
+        "commit".to_string(),
+        "--amend".to_string(),
+        "--no-edit".to_string(),
+    ];
+    if options.sign {
+        args.push("-S".to_string());
+    }
+    if options.no_verify {
+        args.push("--no-verify".to_string());
+    }
+    if !options.only.is_empty() {
+        args.push("--".to_string());
+        args.extend(options.only.iter().cloned());
+    }
+    args
+}
+
+/// Fold staged changes into the last commit, keeping its message
+///
+/// Runs `git commit --amend --no-edit` (see [`build_amend_keep_message_args`]),
+/// so no message is generated. This rewrites history: do not amend commits
+/// that have already been pushed to a shared branch.
+///
+/// # Errors
+///
+/// * Failed to execute git command
+/// * No commit to amend
+/// * Commit validation failed (e.g. pre-commit hook)
+pub fn run_git_amend_keep_message(options: &CommitOptions) -> Result<()> {
+    get_amend_base()?;
+
+    let status = git_command()
+        .args(build_amend_keep_message_args(options))
+        .status()
+        .map_err(|e| Error::io("Failed to execute git commit command", e))?;
+
+    if !status.success() {
+        return Err(Error::CommitFailed {
+            code: status.code(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Get the revision an amended commit should be diffed against
+///
+/// Returns `HEAD~1` when HEAD has a parent, or git's empty tree object when
+/// HEAD is the root commit, so the diff covers everything in the commit
+/// being amended.
+///
+/// # Errors
+///
+/// * Failed to execute git command
+/// * There is no HEAD commit to amend
+pub fn get_amend_base() -> Result<String> {
+    let head = git_command()
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .output()
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
+    if !head.status.success() {
+        return Err(Error::NoCommitToAmend);
+    }
+
+    let parent = git_command()
+        .args(["rev-parse", "--verify", "--quiet", "HEAD~1"])
+        .output()
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
+    if parent.status.success() {
+        Ok("HEAD~1".to_string())
+    } else {
+        Ok(EMPTY_TREE.to_string())
+    }
+}
+
+/// Resolve a `--since` value to the revision the staged changes are diffed against
+///
+/// A revision such as `main` or `HEAD~3` is returned as is. Anything that
+/// looks like a date (`yesterday`, `2024-05-01`, `3 days ago`) resolves to
+/// the last commit on HEAD before it, so the diff covers the commits made
+/// since then plus the staged changes.
+///
+/// # Errors
+///
+/// * The value is neither a valid revision nor a date (git's message is included)
+/// * There is no commit on HEAD before the date
+pub fn resolve_since(since: &str) -> Result<String> {
+    let revision = git_command()
+        .args(["rev-parse", "--verify", &format!("{}^{{commit}}", since)])
+        .output()
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
+    if revision.status.success() {
+        return Ok(since.to_string());
+    }
+    if !looks_like_date(since) {
+        return Err(git_failed(
+            &format!("'{}' is not a valid revision or date", since),
+            &revision,
+        ));
+    }
+
+    let output = git_command()
+        .args(build_since_args(since))
+        .output()
+        .map_err(|e| Error::io("Failed to execute git command", e))?;
+    if !output.status.success() {
+        return Err(git_failed(
+            &format!("Failed to find a commit before '{}'", since),
+            &output,
+        ));
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if commit.is_empty() {
+        return Err(Error::GitFailed {
+            action: format!("Failed to find a commit before '{}'", since),
+            stderr: "HEAD has no commits before this date".to_string(),
+        });
+    }
+    Ok(commit)
+}
+
+/// Build the `git rev-list` arguments finding the last commit on HEAD before `date`
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::git::build_since_args;
+///
+/// assert_eq!(
+///     build_since_args("yesterday"),
+///     vec!["rev-list", "-1", "--before=yesterday", "HEAD"]
+/// );
+/// ```
+pub fn build_since_args(date: &str) -> Vec<String> {
+    vec![
+        "rev-list".to_string(),
+        "-1".to_string(),
+        format!("--before={}", date),
+        "HEAD".to_string(),
+    ]
+}
+
+/// Whether a `--since` value that is not a revision should be read as a date
+///
+/// git accepts almost any text as an approximate date, so a mistyped branch
+/// name would silently mean "now"; only values with a digit, a space or a
+/// relative day name are treated as dates.
+fn looks_like_date(value: &str) -> bool {
+    value
+        .chars()
+        .any(|c| c.is_ascii_digit() || c.is_whitespace())
+        || ["now", "today", "yesterday", "noon", "midnight"]
+            .iter()
+            .any(|day| value.eq_ignore_ascii_case(day))
+}
+
+/// Object id of git's empty tree, used as the diff base for root commits
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Run the pre-commit hook if it exists
+///
+/// Executes `pre-commit` from [`get_hooks_dir`] before Claude generates a
+/// commit message, so it is found in submodules and worktrees (where `.git`
+/// is a file), from subdirectories and with `core.hooksPath`.
+/// This catches linter/formatter errors early, avoiding unnecessary API calls.
+/// If the hook does not exist, silently succeeds.
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if hook succeeds or does not exist, Err if hook fails
+///
+/// # Errors
+///
+/// * Not in a git repository
+/// * Hook script fails to execute
+/// * Hook exits with non-zero status
+pub fn run_pre_commit_hook() -> Result<()> {
+    let hook_path = get_hooks_dir()?.join("pre-commit");
+
+    if !hook_path.exists() {
+        return Ok(());
+    }
+
+    log::info("Running pre-commit hook...");
+
+    let status = Command::new(&hook_path)
+        .status()
+        .map_err(|e| Error::io("Failed to execute pre-commit hook", e))?;
+
+    if !status.success() {
+        return Err(Error::PreCommitFailed {
+            code: status.code(),
+        });
+    }
+
+    log::info(color::success("Pre-commit hook passed."));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_diff_mode_git_args() {
+        // Arrange / Act / Assert - each mode maps to its git invocation
+        assert_eq!(DiffMode::Staged.git_args(), vec!["diff", "--cached"]);
+        assert_eq!(DiffMode::Unstaged.git_args(), vec!["diff"]);
+        assert_eq!(DiffMode::All.git_args(), vec!["diff", "HEAD"]);
+    }
+
+    #[test]
+    fn test_diff_mode_default_is_staged() {
+        // Arrange / Act
+        let mode = DiffMode::default();
+
+        // Assert - default must preserve the original `--cached` behavior
+        assert_eq!(mode, DiffMode::Staged);
+    }
+
+    #[test]
+    fn test_build_diff_args_without_pathspecs() {
+        // Arrange
+        let options = DiffOptions::default();
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert - no separator when no paths are given
+        assert_eq!(args, vec!["diff", "--cached"]);
+    }
+
+    #[test]
+    fn test_build_diff_args_with_pathspecs() {
+        // Arrange
+        let options = DiffOptions {
+            pathspecs: vec!["crates/core".to_string(), "README.md".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert - paths follow the `--` separator in order
+        assert_eq!(
+            args,
+            vec!["diff", "--cached", "--", "crates/core", "README.md"]
+        );
+    }
+
+    #[test]
+    fn test_build_diff_args_with_excludes() {
+        // Arrange - only exclusions, no positive pathspecs
+        let options = DiffOptions {
+            exclude_paths: vec![
+                "Cargo.lock".to_string(),
+                "package-lock.json".to_string(),
+                "*.snap".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert - each pattern becomes an `:(exclude)` pathspec after `--`
+        assert_eq!(
+            args,
+            vec![
+                "diff",
+                "--cached",
+                "--",
+                ":(exclude)Cargo.lock",
+                ":(exclude)package-lock.json",
+                ":(exclude)*.snap",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_diff_args_with_pathspecs_and_excludes() {
+        // Arrange
+        let options = DiffOptions {
+            pathspecs: vec!["app/".to_string()],
+            exclude_paths: vec!["app/yarn.lock".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert - positive pathspecs come before exclusions, single separator
+        assert_eq!(
+            args,
+            vec!["diff", "--cached", "--", "app/", ":(exclude)app/yarn.lock"]
+        );
+    }
+
+    const TEXT_SECTION: &str = "diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@
+-fn main() {}
++fn main() { println!(\"Binary files a and b differ\"); }
+";
+
+    #[test]
+    fn test_split_diff_by_file_roundtrip() {
+        // Arrange - two file sections
+        let diff = format!(
+            "{}{}",
+            TEXT_SECTION,
+            TEXT_SECTION.replace("main.rs", "lib.rs")
+        );
+
+        // Act
+        let sections = split_diff_by_file(&diff);
+
+        // Assert - one section per file, concatenation yields original
+        assert_eq!(sections.len(), 2);
+        assert!(sections[1].starts_with("diff --git a/src/lib.rs"));
+        assert_eq!(sections.concat(), diff);
+    }
+
+    #[test]
+    fn test_strip_binary_diffs_text_only_untouched() {
+        // Arrange - text diff only (mentions "Binary files" inside a hunk)
+        let diff = TEXT_SECTION.trim_end();
+
+        // Act
+        let result = strip_binary_diffs(diff);
+
+        // Assert - completely unchanged
+        assert_eq!(result, diff);
+    }
+
+    #[test]
+    fn test_normalize_line_endings_crlf() {
+        // Arrange
+        let diff = "diff --git a/a.txt b/a.txt\r\n-old\r\n+new\r\n";
+
+        // Act
+        let result = normalize_line_endings(diff);
+
+        // Assert
+        assert_eq!(result, "diff --git a/a.txt b/a.txt\n-old\n+new\n");
+    }
+
+    #[test]
+    fn test_normalize_line_endings_leaves_lf_and_lone_cr() {
+        // Arrange - LF-only lines and a carriage return inside a line
+        let diff = "+a\n+b\r+c\n";
+
+        // Act / Assert
+        assert_eq!(normalize_line_endings(diff), diff);
+    }
+
+    #[test]
+    fn test_mark_new_files() {
+        // Arrange - a new file between two modified files
+        let diff = format!(
+            "{}diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..1111111
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1 @@
++pub fn new() {{}}
+{}",
+            TEXT_SECTION, TEXT_SECTION
+        );
+
+        // Act
+        let result = mark_new_files(&diff);
+
+        // Assert - only the new file is labeled, right after its header
+        assert_eq!(
+            result,
+            diff.replacen(
+                "diff --git a/src/new.rs b/src/new.rs\n",
+                "diff --git a/src/new.rs b/src/new.rs\n[NEW FILE]\n",
+                1
+            )
+        );
+        assert_eq!(result.matches(NEW_FILE_MARKER).count(), 1);
+    }
+
+    const INTENT_TO_ADD_SECTION: &str = "diff --git a/src/todo.rs b/src/todo.rs
+new file mode 100644
+index 0000000..e69de29
+";
+
+    #[test]
+    fn test_skip_intent_to_add_removes_empty_new_file() {
+        // Arrange - an intent-to-add file between two modified files
+        let diff = format!("{}{}{}", TEXT_SECTION, INTENT_TO_ADD_SECTION, TEXT_SECTION);
+
+        // Act
+        let result = skip_intent_to_add(&diff);
+
+        // Assert
+        assert_eq!(
+            result,
+            format!("{}{}", TEXT_SECTION, TEXT_SECTION).trim_end()
+        );
+    }
+
+    #[test]
+    fn test_skip_intent_to_add_also_removes_staged_empty_file() {
+        // Arrange - `git add .gitkeep` of an empty file
+        let diff = "diff --git a/.gitkeep b/.gitkeep\n\
+                    new file mode 100644\n\
+                    index 0000000..e69de29";
+
+        // Act / Assert - documented limitation: indistinguishable from `git add -N`
+        assert_eq!(skip_intent_to_add(diff), "");
+    }
+
+    #[test]
+    fn test_skip_intent_to_add_keeps_new_files_with_content() {
+        // Arrange - new text and binary files have content
+        let diff = "diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..1111111
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1 @@
++pub fn new() {}
+diff --git a/logo.png b/logo.png
+new file mode 100644
+index 0000000..2222222
+Binary files /dev/null and b/logo.png differ";
+
+        // Act / Assert
+        assert_eq!(skip_intent_to_add(diff), diff);
+    }
+
+    #[test]
+    fn test_get_git_diff_skips_intent_to_add_when_enabled() {
+        // Arrange
+        let runner = crate::runner::fake::FakeRunner::default().respond(
+            &git_binary(),
+            &["diff"],
+            0,
+            &format!("{}{}", TEXT_SECTION, INTENT_TO_ADD_SECTION),
+        );
+        let enabled = DiffOptions {
+            skip_intent_to_add: true,
+            ..DiffOptions::default()
+        };
+
+        // Act
+        let skipped = get_git_diff_with(&runner, &enabled).unwrap();
+        let kept = get_git_diff_with(&runner, &DiffOptions::default()).unwrap();
+
+        // Assert - the header only disappears with the flag on
+        assert_eq!(skipped, TEXT_SECTION.trim_end());
+        assert!(kept.contains("src/todo.rs"));
+    }
+
+    #[test]
+    fn test_strip_binary_diffs_multiple_binary_files() {
+        // Arrange - added, modified and deleted binaries around a text change
+        let diff = format!(
+            "diff --git a/img/new.png b/img/new.png
+new file mode 100644
+index 0000000..1111111
+Binary files /dev/null and b/img/new.png differ
+{}diff --git a/font.woff b/font.woff
+index 2222222..3333333 100644
+Binary files a/font.woff and b/font.woff differ
+diff --git a/old.jpg b/old.jpg
+deleted file mode 100644
+index 4444444..0000000
+Binary files a/old.jpg and /dev/null differ",
+            TEXT_SECTION
+        );
+
+        // Act
+        let result = strip_binary_diffs(&diff);
+
+        // Assert - binaries summarized, text section preserved in place
+        assert_eq!(
+            result,
+            format!(
+                "(binary file changed: img/new.png)\n{}(binary file changed: font.woff)\n(binary file changed: old.jpg)",
+                TEXT_SECTION
+            )
+        );
+    }
+
+    #[test]
+    fn test_branch_or_short_sha_on_branch() {
+        // Arrange / Act
+        let branch = branch_or_short_sha("feat/login\n", || panic!("not detached"));
+
+        // Assert
+        assert_eq!(branch.unwrap(), "feat/login");
+    }
+
+    #[test]
+    fn test_get_current_branch_on_unborn_branch() {
+        // Arrange - a new repository: HEAD names `main`, which has no commit yet
+        let runner = crate::runner::fake::FakeRunner::default()
+            .respond(&git_binary(), &["rev-parse"], 128, "")
+            .respond(
+                &git_binary(),
+                &["symbolic-ref", "--short", "HEAD"],
+                0,
+                "main\n",
+            );
+
+        // Act
+        let branch = get_current_branch_with(&runner);
+
+        // Assert
+        assert_eq!(branch.unwrap(), "main");
+    }
+
+    #[test]
+    fn test_get_current_branch_outside_repository_fails() {
+        // Arrange - both lookups fail
+        let runner = crate::runner::fake::FakeRunner::default()
+            .respond(&git_binary(), &["rev-parse"], 128, "")
+            .respond(&git_binary(), &["symbolic-ref"], 128, "");
+
+        // Act
+        let result = get_current_branch_with(&runner);
+
+        // Assert
+        assert!(matches!(result, Err(Error::GitFailed { .. })));
+    }
+
+    #[test]
+    fn test_branch_or_short_sha_detached_head() {
+        // Arrange / Act - git prints `HEAD` when no branch is checked out
+        let branch = branch_or_short_sha("HEAD\n", || Ok("a1b2c3d".to_string()));
+
+        // Assert
+        assert_eq!(branch.unwrap(), "a1b2c3d");
+    }
+
+    #[test]
+    fn test_git_dir_from_output_plain_repository() {
+        // Arrange - output of `git rev-parse --git-dir` at the repository root
+        let stdout = ".git\n";
+
+        // Act
+        let dir = git_dir_from_output(stdout);
+
+        // Assert
+        assert_eq!(dir, PathBuf::from(".git"));
+    }
+
+    #[test]
+    fn test_git_dir_from_output_worktree() {
+        // Arrange - worktree git dirs live under the main repository
+        let stdout = "/repo/.git/worktrees/feature\n";
+
+        // Act
+        let dir = git_dir_from_output(stdout);
+
+        // Assert
+        assert_eq!(dir, PathBuf::from("/repo/.git/worktrees/feature"));
+    }
+
+    #[test]
+    fn test_hooks_dir_in_follows_gitdir_file() {
+        // Arrange - like a submodule or worktree, `linked/.git` is a file
+        // pointing at the real git directory; run from a subdirectory
+        let dir = TempDir::new("hooks_gitdir");
+        let status = git_command()
+            .args(["init", "-q"])
+            .arg(dir.join("main"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::create_dir_all(dir.join("linked/src")).unwrap();
+        fs::write(dir.join("linked/.git"), "gitdir: ../main/.git\n").unwrap();
+
+        // Act
+        let hooks = hooks_dir_in(Some(&dir.join("linked/src"))).unwrap();
+
+        // Assert - the hook is looked up in the real git directory
+        assert_eq!(
+            fs::canonicalize(hooks).unwrap(),
+            fs::canonicalize(dir.join("main/.git/hooks")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_git_dir_from_output_empty_falls_back() {
+        // Arrange / Act
+        let dir = git_dir_from_output("");
+
+        // Assert
+        assert_eq!(dir, PathBuf::from(".git"));
+    }
+
+    #[test]
+    fn test_git_binary_from_override() {
+        // Arrange - value as read from GIT_BINARY
+        let value = Some("/opt/git/bin/git".to_string());
+
+        // Act
+        let program = git_binary_from(value);
+
+        // Assert
+        assert_eq!(program, "/opt/git/bin/git");
+    }
+
+    #[test]
+    fn test_git_binary_from_unset_or_empty() {
+        // Arrange / Act / Assert - unset and empty both fall back to PATH lookup
+        assert_eq!(git_binary_from(None), "git");
+        assert_eq!(git_binary_from(Some("".to_string())), "git");
+    }
+
+    #[test]
+    fn test_parse_name_only_multiple_files() {
+        // Arrange
+        let stdout = "src/main.rs\nREADME.md\n";
+
+        // Act
+        let files = parse_name_only(stdout);
+
+        // Assert
+        assert_eq!(files, vec!["src/main.rs", "README.md"]);
+    }
+
+    #[test]
+    fn test_parse_name_only_empty_output() {
+        // Arrange / Act
+        let files = parse_name_only("\n");
+
+        // Assert - no phantom empty entry
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_build_diff_stat_args_before_separator() {
+        // Arrange
+        let options = DiffOptions {
+            exclude_paths: vec!["Cargo.lock".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_stat_args(&options);
+
+        // Assert - `--stat` is an option, so it must precede `--`
+        assert_eq!(
+            args,
+            vec!["diff", "--cached", "--stat", "--", ":(exclude)Cargo.lock"]
+        );
+    }
+
+    #[test]
+    fn test_build_diff_args_with_zero_context_lines() {
+        // Arrange
+        let options = DiffOptions {
+            context_lines: Some(0),
+            pathspecs: vec!["src/".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert - `-U0` is passed as an option before the separator
+        assert_eq!(args, vec!["diff", "--cached", "-U0", "--", "src/"]);
+    }
+
+    #[test]
+    fn test_build_diff_args_with_word_diff() {
+        // Arrange
+        let options = DiffOptions {
+            context_lines: Some(3),
+            diff_algorithm: DiffAlgorithm::Word,
+            pathspecs: vec!["README.md".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert - an option, so it goes before the separator
+        assert_eq!(
+            args,
+            vec!["diff", "--cached", "-U3", "--word-diff", "--", "README.md"]
+        );
+    }
+
+    #[test]
+    fn test_build_diff_args_with_range() {
+        // Arrange - the range replaces `--cached` and the revision
+        let options = DiffOptions {
+            range: Some("main..HEAD".to_string()),
+            revision: Some("HEAD~1".to_string()),
+            context_lines: Some(3),
+            exclude_paths: vec!["Cargo.lock".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+        let stat_args = build_diff_stat_args(&options);
+
+        // Assert
+        assert_eq!(
+            args,
+            vec!["diff", "main..HEAD", "-U3", "--", ":(exclude)Cargo.lock"]
+        );
+        assert_eq!(
+            stat_args,
+            vec![
+                "diff",
+                "--stat",
+                "main..HEAD",
+                "-U3",
+                "--",
+                ":(exclude)Cargo.lock"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_range_rejects_empty_range() {
+        // Arrange / Act / Assert
+        assert!(matches!(validate_range(""), Err(Error::EmptyRange)));
+        assert!(matches!(validate_range("  "), Err(Error::EmptyRange)));
+    }
+
+    #[test]
+    fn test_get_git_diff_rejects_option_like_range() {
+        // Arrange - a runner that would answer anything
+        let runner = crate::runner::fake::FakeRunner::default().respond(&git_binary(), &[], 0, "");
+        let options = DiffOptions {
+            range: Some("--output=/tmp/pwned".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result = get_git_diff_with(&runner, &options);
+
+        // Assert - refused before running git
+        assert!(matches!(result, Err(Error::InvalidRange(_))));
+        assert!(runner.programs().is_empty());
+    }
+
+    #[test]
+    fn test_build_diff_args_with_revision() {
+        // Arrange - diff for amending: index against HEAD's parent
+        let options = DiffOptions {
+            revision: Some("HEAD~1".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert
+        assert_eq!(args, vec!["diff", "--cached", "HEAD~1"]);
+    }
+
+    #[test]
+    fn test_build_since_args() {
+        // Arrange / Act
+        let args = build_since_args("2024-05-01 09:00");
+
+        // Assert - the date stays a single argument
+        assert_eq!(
+            args,
+            vec!["rev-list", "-1", "--before=2024-05-01 09:00", "HEAD"]
+        );
+    }
+
+    #[test]
+    fn test_build_diff_args_with_since_revision() {
+        // Arrange - the resolved --since revision combined with the staged changes
+        let options = DiffOptions {
+            revision: Some("a1b2c3d".to_string()),
+            pathspecs: vec!["src/".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_diff_args(&options);
+
+        // Assert
+        assert_eq!(args, vec!["diff", "--cached", "a1b2c3d", "--", "src/"]);
+    }
+
+    #[test]
+    fn test_looks_like_date() {
+        // Arrange / Act / Assert
+        assert!(looks_like_date("2024-05-01"));
+        assert!(looks_like_date("3 days ago"));
+        assert!(looks_like_date("Yesterday"));
+        assert!(!looks_like_date("mian"));
+        assert!(!looks_like_date("feature/login"));
+    }
+
+    #[test]
+    fn test_build_commit_args_edit_and_direct() {
+        // Arrange
+        let options = CommitOptions::default();
+
+        // Act
+        let edit = build_commit_args(".git/COMMIT_MSG_GENERATED", true, &options);
+        let direct = build_commit_args(".git/COMMIT_MSG_GENERATED", false, &options);
+
+        // Assert - existing invocations are unchanged
+        assert_eq!(
+            edit,
+            vec!["commit", "-v", "-e", "-F", ".git/COMMIT_MSG_GENERATED"]
+        );
+        assert_eq!(direct, vec!["commit", "-F", ".git/COMMIT_MSG_GENERATED"]);
+    }
+
+    #[test]
+    fn test_build_commit_args_amend() {
+        // Arrange
+        let options = CommitOptions {
+            amend: true,
+            ..Default::default()
+        };
+
+        // Act
+        let direct = build_commit_args("msg", false, &options);
+        let edit = build_commit_args("msg", true, &options);
+
+        // Assert
+        assert_eq!(direct, vec!["commit", "--amend", "-F", "msg"]);
+        assert_eq!(edit, vec!["commit", "--amend", "-v", "-e", "-F", "msg"]);
+    }
+
+    #[test]
+    fn test_build_commit_args_sign() {
+        // Arrange
+        let options = CommitOptions {
+            amend: true,
+            sign: true,
+            ..Default::default()
+        };
+
+        // Act
+        let direct = build_commit_args("msg", false, &options);
+        let edit = build_commit_args("msg", true, &options);
+
+        // Assert
+        assert_eq!(direct, vec!["commit", "--amend", "-S", "-F", "msg"]);
+        assert_eq!(
+            edit,
+            vec!["commit", "--amend", "-S", "-v", "-e", "-F", "msg"]
+        );
+    }
+
+    #[test]
+    fn test_build_file_diff_args_single_path() {
+        // Arrange / Act - a path that looks like an option stays after `--`
+        let args = build_file_diff_args("-weird name.txt");
+
+        // Assert
+        assert_eq!(args, vec!["diff", "--cached", "--", "-weird name.txt"]);
+    }
+
+    #[test]
+    fn test_build_amend_keep_message_args() {
+        // Arrange
+        let options = CommitOptions {
+            sign: true,
+            no_verify: true,
+            only: vec!["src/a.rs".to_string()],
+            write_editmsg: true,
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_amend_keep_message_args(&options);
+
+        // Assert - no message file is passed, so the existing message is kept
+        assert_eq!(
+            args,
+            vec![
+                "commit",
+                "--amend",
+                "--no-edit",
+                "-S",
+                "--no-verify",
+                "--",
+                "src/a.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_commit_args_no_verify() {
+        // Arrange
+        let options = CommitOptions {
+            no_verify: true,
+            ..Default::default()
+        };
+
+        // Act
+        let direct = build_commit_args("msg", false, &options);
+        let default = build_commit_args("msg", false, &CommitOptions::default());
+
+        // Assert - only added when requested
+        assert_eq!(direct, vec!["commit", "--no-verify", "-F", "msg"]);
+        assert!(!default.contains(&"--no-verify".to_string()));
+    }
+
+    #[test]
+    fn test_build_commit_args_with_only_paths() {
+        // Arrange
+        let options = CommitOptions {
+            only: vec!["src/a.rs".to_string(), "docs/b.md".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let direct = build_commit_args("msg", false, &options);
+        let edit = build_commit_args("msg", true, &options);
+
+        // Assert - paths come last, after the separator
+        assert_eq!(
+            direct,
+            vec!["commit", "-F", "msg", "--", "src/a.rs", "docs/b.md"]
+        );
+        assert_eq!(
+            edit,
+            vec![
+                "commit",
+                "-v",
+                "-e",
+                "-F",
+                "msg",
+                "--",
+                "src/a.rs",
+                "docs/b.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_commit_args_ignores_write_editmsg() {
+        // Arrange
+        let options = CommitOptions {
+            write_editmsg: true,
+            ..Default::default()
+        };
+
+        // Act
+        let args = build_commit_args("msg", true, &options);
+
+        // Assert - the message is still passed with -F
+        assert_eq!(args, vec!["commit", "-v", "-e", "-F", "msg"]);
+    }
+
+    #[test]
+    fn test_parse_ignore_file_skips_comments_and_blank_lines() {
+        // Arrange
+        let content = "# generated files\nCargo.lock\n\n  dist/  \n# docs\n*.min.js\n";
+
+        // Act
+        let patterns = parse_ignore_file(content);
+
+        // Assert
+        assert_eq!(patterns, vec!["Cargo.lock", "dist/", "*.min.js"]);
+    }
+
+    #[test]
+    fn test_parse_ignore_file_empty() {
+        // Arrange / Act / Assert
+        assert!(parse_ignore_file("").is_empty());
+        assert!(parse_ignore_file("# only comments\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_count_staged() {
+        // Arrange - output of `git add -A --verbose`
+        let stdout = "add 'src/main.rs'\nadd 'README.md'\nremove 'old.txt'\n";
+
+        // Act / Assert
+        assert_eq!(count_staged(stdout), 3);
+        assert_eq!(count_staged(""), 0);
+    }
+
+    #[test]
+    fn test_find_conflict_markers_in_added_lines() {
+        // Arrange
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n\
+                    --- a/src/a.rs\n\
+                    +++ b/src/a.rs\n\
+                    @@ -1 +1,5 @@\n\
+                    +<<<<<<< HEAD\n\
+                    +let x = 1;\n\
+                    +=======\n\
+                    +let x = 2;\n\
+                    +>>>>>>> feature\n\
+                    diff --git a/src/b.rs b/src/b.rs\n\
+                    +fn b() {}\n\
+                    diff --git a/notes.md b/notes.md\n\
+                    +=======\n";
+
+        // Act
+        let files = find_conflict_markers(diff);
+
+        // Assert
+        assert_eq!(files, vec!["src/a.rs", "notes.md"]);
+    }
+
+    #[test]
+    fn test_find_conflict_markers_clean_diff() {
+        // Arrange - removed markers and longer rules are not conflicts
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n\
+                    -<<<<<<< HEAD\n\
+                    -=======\n\
+                    ->>>>>>> feature\n\
+                    +let x = 2;\n\
+                    diff --git a/README.md b/README.md\n\
+                    +Title\n\
+                    +==========\n\
+                    +// <<<<<<<< not a marker\n";
+
+        // Act
+        let files = find_conflict_markers(diff);
+
+        // Assert
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_read_commit_editmsg_strips_comments() {
+        // Arrange
+        let dir = TempDir::new("editmsg");
+        fs::write(
+            dir.join("COMMIT_EDITMSG"),
+            "feat: add login\n\nSupport OAuth.\n\
+             # Please enter the commit message for your changes.\n\
+             # On branch main\n\
+             # ------------------------ >8 ------------------------\n\
+             diff --git a/a.rs b/a.rs\n",
+        )
+        .unwrap();
+
+        // Act
+        let message = read_commit_editmsg(dir.path());
+
+        // Assert
+        assert_eq!(
+            message.as_deref(),
+            Some("feat: add login\n\nSupport OAuth.")
+        );
+    }
+
+    #[test]
+    fn test_read_commit_editmsg_missing_file() {
+        // Arrange
+        let dir = TempDir::new("editmsg_missing");
+
+        // Act / Assert
+        assert_eq!(read_commit_editmsg(dir.path()), None);
+    }
+
+    #[test]
+    fn test_limit_lines_per_file_truncates_at_hunk_boundary() {
+        // Arrange - the second hunk would exceed the limit; b.rs is unaffected
+        let diff = "diff --git a/big.sql b/big.sql\n\
+                    --- a/big.sql\n\
+                    +++ b/big.sql\n\
+                    @@ -1,2 +1,2 @@\n\
+                    -a\n\
+                    +b\n\
+                    @@ -10,3 +10,3 @@\n\
+                    +c\n\
+                    +d\n\
+                    +e\n\
+                    diff --git a/b.rs b/b.rs\n\
+                    @@ -1 +1 @@\n\
+                    +f\n";
+
+        // Act
+        let result = limit_lines_per_file(diff, 4);
+
+        // Assert
+        assert_eq!(
+            result,
+            "diff --git a/big.sql b/big.sql\n\
+             --- a/big.sql\n\
+             +++ b/big.sql\n\
+             @@ -1,2 +1,2 @@\n\
+             -a\n\
+             +b\n\
+             ... [4 more lines omitted]\n\
+             diff --git a/b.rs b/b.rs\n\
+             @@ -1 +1 @@\n\
+             +f\n"
+        );
+    }
+
+    #[test]
+    fn test_limit_lines_per_file_cuts_oversized_first_hunk() {
+        // Arrange
+        let diff = "diff --git a/snap.txt b/snap.txt\n@@ -0,0 +1,4 @@\n+1\n+2\n+3\n+4";
+
+        // Act
+        let result = limit_lines_per_file(diff, 3);
+
+        // Assert
+        assert_eq!(
+            result,
+            "diff --git a/snap.txt b/snap.txt\n@@ -0,0 +1,4 @@\n+1\n+2\n... [2 more lines omitted]"
+        );
+    }
+
+    #[test]
+    fn test_limit_lines_per_file_under_limit_unchanged() {
+        // Arrange
+        let diff = "diff --git a/a.rs b/a.rs\n@@ -1 +1 @@\n-old\n+new\n\
+                    diff --git a/logo.png b/logo.png\nBinary files a/logo.png and b/logo.png differ";
+
+        // Act
+        let result = limit_lines_per_file(diff, 3);
+
+        // Assert
+        assert_eq!(result, diff);
+    }
+
+    #[test]
+    fn test_special_state_in_detects_markers() {
+        // Arrange
+        let base = TempDir::new("special_state");
+        let clean = base.join("clean");
+        let merging = base.join("merging");
+        let rebasing = base.join("rebasing");
+        let applying = base.join("applying");
+        fs::create_dir_all(&clean).unwrap();
+        fs::create_dir_all(&merging).unwrap();
+        fs::write(merging.join("MERGE_HEAD"), "abc123\n").unwrap();
+        fs::create_dir_all(rebasing.join("rebase-merge")).unwrap();
+        fs::create_dir_all(applying.join("rebase-apply")).unwrap();
+
+        // Act
+        let states = [&clean, &merging, &rebasing, &applying].map(|dir| special_state_in(dir));
+
+        // Assert
+        assert_eq!(
+            states,
+            [
+                None,
+                Some("merge".to_string()),
+                Some("rebase".to_string()),
+                Some("rebase".to_string())
+            ]
+        );
+    }
 }
@@ -6,30 +6,76 @@
 //! # Modules
 //!
 //! - [`config`] - Configuration file loading and parsing
+//! - [`color`] - ANSI colors for status messages
+//! - [`clipboard`] - Copying messages to the system clipboard (`clipboard` feature)
 //! - [`output`] - Output structures for JSON formatting
 //! - [`claude`] - Claude AI integration for message generation
+//! - [`backend`] - Pluggable message generation backends
+//! - [`cache`] - Cache of generated messages for unchanged prompts
 //! - [`git`] - Git operations (diff, commit, etc.)
 //! - [`prompt`] - Prompt construction and validation
+//! - [`redact`] - Masking secrets in diffs before they are sent
+//! - [`runner`] - Replaceable process launcher for the git queries that build the prompt
+//! - [`lint`] - Commit message format checks
+//! - [`trailer`] - Git trailers appended to generated messages
+//! - [`log`] - Status messages controlled by `--quiet` / `--verbose`
+//! - [`pipeline`] - End-to-end generation in a single call ([`run_pipeline`])
+//! - [`error`] - Error type returned by library functions ([`Error`])
 //!
 //! # Example
 //!
 //! ```no_run
-//! use claude_commit::{config::load_config, git::get_git_diff, claude::generate_message};
+//! use claude_commit::{PipelineOptions, run_pipeline};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let message = run_pipeline("prompt.toml", PipelineOptions::default()).await?;
+//! println!("Generated message: {}", message);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The individual steps can also be wired together by hand:
+//!
+//! ```no_run
+//! use claude_commit::{
+//!     claude::generate_message,
+//!     config::load_config,
+//!     git::{DiffOptions, get_git_diff},
+//!     prompt::PromptContext,
+//! };
 //!
 //! # #[tokio::main]
 //! # async fn main() -> anyhow::Result<()> {
 //! let config = load_config("prompt.toml")?;
-//! let diff = get_git_diff()?;
-//! let message = generate_message(&diff, &config).await?;
+//! let diff = get_git_diff(&DiffOptions::from_config(&config))?;
+//! let message = generate_message(&diff, &config, &PromptContext::default()).await?;
 //! println!("Generated message: {}", message);
 //! # Ok(())
 //! # }
 //! ```
 
+pub mod backend;
+pub mod cache;
 pub mod claude;
 pub mod cli;
+pub mod clipboard;
+pub mod color;
 pub mod config;
+pub mod error;
 pub mod git;
+pub mod lint;
+pub mod log;
 pub mod output;
+pub mod pipeline;
 pub mod prompt;
+pub mod redact;
+pub mod runner;
+pub mod trailer;
 pub mod ui;
+
+#[cfg(test)]
+mod test_support;
+
+pub use error::{Error, Result};
+pub use pipeline::{PipelineOptions, run_pipeline};
@@ -0,0 +1,637 @@
+//! Commit message linting
+//!
+//! This module checks generated messages against the Conventional Commits
+//! format (or a `message_template`) so that malformed output can be
+//! reported or regenerated, and wraps long body lines.
+
+use crate::error::{Error, Result};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Maximum length of the subject line in characters
+pub const MAX_SUBJECT_LENGTH: usize = 72;
+
+/// Check that a message follows the Conventional Commits format
+///
+/// The subject line must look like `type(scope): description` (the scope
+/// and a breaking-change `!` before the colon are optional), be at most
+/// [`MAX_SUBJECT_LENGTH`] characters, and be separated from the body by a
+/// blank line.
+///
+/// # Errors
+///
+/// Returns an error listing every violation, one per line.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::lint::validate_conventional;
+///
+/// assert!(validate_conventional("feat(cli): add --json flag").is_ok());
+/// assert!(validate_conventional("Added a flag").is_err());
+/// ```
+pub fn validate_conventional(message: &str) -> Result<()> {
+    let violations = conventional_violations(message);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::LintFailed { violations })
+    }
+}
+
+/// Check that the subject's commit type is one of `allowed`
+///
+/// The type is the part of the subject before `(`, `!` or `:`. An empty
+/// `allowed` list accepts any message.
+///
+/// # Errors
+///
+/// * The subject has no type, or a type that is not in `allowed`
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::lint::validate_allowed_type;
+///
+/// let allowed = vec!["feat".to_string(), "fix".to_string()];
+/// assert!(validate_allowed_type("fix(cli): handle empty diff", &allowed).is_ok());
+/// assert!(validate_allowed_type("chore: bump deps", &allowed).is_err());
+/// ```
+pub fn validate_allowed_type(message: &str, allowed: &[String]) -> Result<()> {
+    if allowed.is_empty() {
+        return Ok(());
+    }
+    let subject = subject_line(message);
+    let violation = match subject_type(subject) {
+        Some(commit_type) if allowed.iter().any(|t| t == commit_type) => return Ok(()),
+        Some(commit_type) => format!(
+            "type '{}' is not allowed (allowed: {})",
+            commit_type,
+            allowed.join(", ")
+        ),
+        None => format!(
+            "subject has no commit type (allowed: {})",
+            allowed.join(", ")
+        ),
+    };
+    Err(Error::LintFailed {
+        violations: vec![violation],
+    })
+}
+
+/// The commit type of a `type(scope)!: description` subject, if it has one
+fn subject_type(subject: &str) -> Option<&str> {
+    let (prefix, _) = subject.split_once(':')?;
+    let commit_type = prefix
+        .split_once('(')
+        .map_or(prefix, |(commit_type, _)| commit_type)
+        .trim_end_matches('!');
+    (!commit_type.is_empty() && !commit_type.contains(char::is_whitespace)).then_some(commit_type)
+}
+
+/// A `{placeholder}` in a message template
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{[A-Za-z_][A-Za-z0-9_]*\}").unwrap());
+
+/// Check that a message has the structure of a `message_template`
+///
+/// The subject line must match the template's first line, where each
+/// `{placeholder}` stands for any non-empty text and everything else is
+/// literal. When the template has a blank line after the subject, so must
+/// the message; a single-line template allows no body.
+///
+/// # Errors
+///
+/// Returns an error listing every violation, one per line.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::lint::validate_template;
+///
+/// let template = "{type}: {summary}\n\n{body}";
+/// assert!(validate_template("fix: handle empty diff\n\nDetails", template).is_ok());
+/// assert!(validate_template("Handle empty diff", template).is_err());
+/// ```
+pub fn validate_template(message: &str, template: &str) -> Result<()> {
+    let violations = template_violations(message, template);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::LintFailed { violations })
+    }
+}
+
+/// Collect every deviation of a message from the template's structure
+fn template_violations(message: &str, template: &str) -> Vec<String> {
+    let mut violations = Vec::new();
+    let mut template_lines = template.lines();
+    let template_subject = template_lines.next().unwrap_or("");
+    let mut lines = skip_leading_blank_lines(message).lines();
+    let subject = lines.next().unwrap_or("");
+
+    if !subject_pattern(template_subject).is_match(subject) {
+        violations.push(format!(
+            "subject does not match the template `{}`",
+            template_subject
+        ));
+    }
+
+    match (template_lines.next(), lines.next()) {
+        (None, Some(_)) => {
+            violations.push("the template allows only a subject line".to_string());
+        }
+        (Some(blank), Some(second)) if blank.trim().is_empty() && !second.trim().is_empty() => {
+            violations.push("subject and body must be separated by a blank line".to_string());
+        }
+        _ => {}
+    }
+
+    violations
+}
+
+/// Build an anchored regex from a template line, with placeholders matching any text
+fn subject_pattern(template_line: &str) -> Regex {
+    let mut pattern = String::from("^");
+    let mut literal_start = 0;
+    for placeholder in PLACEHOLDER.find_iter(template_line) {
+        pattern.push_str(&regex::escape(
+            &template_line[literal_start..placeholder.start()],
+        ));
+        pattern.push_str(".+");
+        literal_start = placeholder.end();
+    }
+    pattern.push_str(&regex::escape(&template_line[literal_start..]));
+    pattern.push('$');
+    // Every non-placeholder character is escaped, so the pattern is always valid
+    Regex::new(&pattern).unwrap()
+}
+
+/// Check that the subject line is at most `max` characters long
+///
+/// Length is counted in characters rather than bytes, so CJK subjects are
+/// measured the way they are displayed.
+///
+/// # Errors
+///
+/// * The subject line is longer than `max` characters
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::lint::check_subject_length;
+///
+/// assert!(check_subject_length("feat: 日本語の件名", 12).is_ok());
+/// assert!(check_subject_length("feat: a long subject", 12).is_err());
+/// ```
+pub fn check_subject_length(message: &str, max: usize) -> Result<()> {
+    let length = subject_length(message);
+    if length > max {
+        return Err(Error::SubjectTooLong { length, max });
+    }
+    Ok(())
+}
+
+/// Hard-wrap the body of a commit message at `width` characters
+///
+/// Leading blank lines, the subject line (the first non-blank line), blank
+/// lines, lines that already fit and lines inside
+/// ```` ``` ```` fenced code blocks are kept as they are. Long lines are
+/// broken at whitespace; list items (`- `, `* `, `1. `) and indented lines
+/// continue with a hanging indent. A single word longer than `width`, such
+/// as a URL, is left on its own line.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::lint::wrap_commit_body;
+///
+/// let message = "feat: add wrapping\n\n- wrap long body lines at the configured width";
+/// assert_eq!(
+///     wrap_commit_body(message, 25),
+///     "feat: add wrapping\n\n- wrap long body lines at\n  the configured width"
+/// );
+/// ```
+pub fn wrap_commit_body(message: &str, width: usize) -> String {
+    let rest = skip_leading_blank_lines(message);
+    let leading = &message[..message.len() - rest.len()];
+    let mut lines = rest.lines();
+    let mut result: Vec<String> = lines.next().map(str::to_string).into_iter().collect();
+    let mut in_fence = false;
+
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            result.push(line.to_string());
+        } else if in_fence || line.chars().count() <= width {
+            result.push(line.to_string());
+        } else {
+            result.extend(wrap_line(line, width));
+        }
+    }
+
+    let mut wrapped = format!("{}{}", leading, result.join("\n"));
+    if rest.ends_with('\n') {
+        wrapped.push('\n');
+    }
+    wrapped
+}
+
+/// Break one line at whitespace, indenting continuation lines under its text
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let content = line.trim_start();
+    let prefix_len = line.len() - content.len() + list_marker_len(content);
+    let (prefix, text) = line.split_at(prefix_len);
+    let hanging = " ".repeat(prefix.chars().count());
+
+    let mut lines = Vec::new();
+    let mut current = prefix.to_string();
+    let mut current_len = current.chars().count();
+    let mut has_word = false;
+    for word in text.split_whitespace() {
+        let word_len = word.chars().count();
+        if has_word && current_len + 1 + word_len > width {
+            lines.push(std::mem::replace(&mut current, hanging.clone()));
+            current_len = hanging.len();
+            has_word = false;
+        }
+        if has_word {
+            current.push(' ');
+            current_len += 1;
+        }
+        current.push_str(word);
+        current_len += word_len;
+        has_word = true;
+    }
+    lines.push(current);
+    lines
+}
+
+/// Length in bytes of a leading `- `, `* `, `+ ` or `1. ` list marker, or 0
+fn list_marker_len(text: &str) -> usize {
+    if let Some(rest) = text.strip_prefix(['-', '*', '+'])
+        && rest.starts_with(' ')
+    {
+        return 2;
+    }
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && text[digits..].starts_with(". ") {
+        return digits + 2;
+    }
+    0
+}
+
+/// `message` from its first non-blank line on
+///
+/// With `trim_output = "trim_end"` or `"none"` a response can start with
+/// blank lines, which git drops on commit, so the subject is the first
+/// line with text.
+fn skip_leading_blank_lines(message: &str) -> &str {
+    let mut rest = message;
+    while let Some((line, tail)) = rest.split_once('\n')
+        && line.trim().is_empty()
+    {
+        rest = tail;
+    }
+    rest
+}
+
+/// The subject line: the first non-blank line, or `""` for a blank message
+fn subject_line(message: &str) -> &str {
+    skip_leading_blank_lines(message)
+        .lines()
+        .next()
+        .unwrap_or("")
+}
+
+/// Length of the subject line in characters
+fn subject_length(message: &str) -> usize {
+    subject_line(message).chars().count()
+}
+
+/// Collect every Conventional Commits violation in a message
+fn conventional_violations(message: &str) -> Vec<String> {
+    let mut violations = Vec::new();
+    let mut lines = skip_leading_blank_lines(message).lines();
+    let subject = lines.next().unwrap_or("");
+
+    if let Err(reason) = check_subject_format(subject) {
+        violations.push(format!(
+            "subject does not match `type(scope): description`: {}",
+            reason
+        ));
+    }
+
+    if let Err(err) = check_subject_length(message, MAX_SUBJECT_LENGTH) {
+        violations.push(err.to_string());
+    }
+
+    if let Some(second) = lines.next()
+        && !second.trim().is_empty()
+    {
+        violations.push("subject and body must be separated by a blank line".to_string());
+    }
+
+    violations
+}
+
+/// Check the `type(scope)!: description` shape of a subject line
+fn check_subject_format(subject: &str) -> Result<(), &'static str> {
+    let Some((prefix, description)) = subject.split_once(':') else {
+        return Err("missing `:` after the type");
+    };
+
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let commit_type = match prefix.split_once('(') {
+        Some((commit_type, scope)) => {
+            let Some(scope) = scope.strip_suffix(')') else {
+                return Err("unclosed scope parenthesis");
+            };
+            if scope.is_empty() || scope.contains(['(', ')']) {
+                return Err("invalid scope");
+            }
+            commit_type
+        }
+        None => prefix,
+    };
+
+    if commit_type.is_empty() || !commit_type.chars().all(|c| c.is_ascii_lowercase()) {
+        return Err("type must be lowercase letters");
+    }
+    if !description.starts_with(' ') || description.trim().is_empty() {
+        return Err("expected `: ` followed by a description");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_conventional_valid_subjects() {
+        // Arrange / Act / Assert
+        assert!(validate_conventional("feat: add login").is_ok());
+        assert!(validate_conventional("fix(parser): handle empty input").is_ok());
+        assert!(validate_conventional("refactor(api)!: drop v1 endpoints").is_ok());
+        assert!(validate_conventional("feat!: remove legacy flag").is_ok());
+    }
+
+    #[test]
+    fn test_validate_conventional_valid_with_body() {
+        // Arrange
+        let message = "docs: update README\n\n- Add install section\n- Fix typos";
+
+        // Act / Assert
+        assert!(validate_conventional(message).is_ok());
+    }
+
+    #[test]
+    fn test_validate_conventional_missing_type() {
+        // Arrange / Act
+        let err = validate_conventional("Add login page")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert!(err.contains("missing `:`"));
+    }
+
+    #[test]
+    fn test_validate_conventional_invalid_type_and_scope() {
+        // Arrange / Act / Assert
+        assert!(validate_conventional("Feat: add login").is_err());
+        assert!(validate_conventional("feat(: add login").is_err());
+        assert!(validate_conventional("feat(): add login").is_err());
+        assert!(validate_conventional("feat:add login").is_err());
+        assert!(validate_conventional("feat: ").is_err());
+    }
+
+    #[test]
+    fn test_validate_conventional_subject_too_long() {
+        // Arrange - 73 characters
+        let subject = format!("feat: {}", "a".repeat(67));
+
+        // Act
+        let err = validate_conventional(&subject).unwrap_err().to_string();
+
+        // Assert
+        assert!(err.contains("73 characters"));
+    }
+
+    #[test]
+    fn test_validate_conventional_missing_blank_line() {
+        // Arrange / Act
+        let err = validate_conventional("feat: add login\n- details")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert!(err.contains("blank line"));
+    }
+
+    #[test]
+    fn test_validate_conventional_reports_all_violations() {
+        // Arrange / Act
+        let message = format!("{}\nbody", "x".repeat(80));
+        let err = validate_conventional(&message).unwrap_err().to_string();
+
+        // Assert - one line per violation
+        assert_eq!(err.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_check_subject_length_counts_characters_not_bytes() {
+        // Arrange - 20 characters but 60 bytes
+        let message = "機能追加：ユーザー認証のログイン画面を実装した\n\n本文";
+        let subject = message.lines().next().unwrap();
+        assert!(subject.len() > 50);
+
+        // Act / Assert - within a 50-character limit despite the byte count
+        assert!(check_subject_length(message, 50).is_ok());
+    }
+
+    #[test]
+    fn test_check_subject_length_too_long() {
+        // Arrange - CJK subject over the limit in characters
+        let cjk = "あ".repeat(51);
+        let ascii = format!("feat: {}", "a".repeat(45));
+
+        // Act
+        let cjk_err = check_subject_length(&cjk, 50).unwrap_err().to_string();
+        let ascii_err = check_subject_length(&ascii, 50).unwrap_err().to_string();
+
+        // Assert
+        assert!(cjk_err.contains("51 characters"));
+        assert!(ascii_err.contains("51 characters"));
+    }
+
+    #[test]
+    fn test_check_subject_length_ignores_body() {
+        // Arrange / Act / Assert
+        let message = format!("feat: short\n\n{}", "a".repeat(200));
+        assert!(check_subject_length(&message, 20).is_ok());
+    }
+
+    #[test]
+    fn test_subject_checks_skip_leading_blank_lines() {
+        // Arrange - a response kept untrimmed by `trim_output = "trim_end"`
+        let message = "\n  \nfeat: add login\n\nBody";
+        let allowed = vec!["feat".to_string()];
+
+        // Act / Assert - the subject is the first line with text
+        assert!(validate_conventional(message).is_ok());
+        assert!(validate_allowed_type(message, &allowed).is_ok());
+        assert!(validate_template(message, "{type}: {summary}\n\n{body}").is_ok());
+        assert!(check_subject_length(message, 10).is_err());
+    }
+
+    #[test]
+    fn test_validate_template_conforming_message() {
+        // Arrange
+        let template = "{type}: {summary}\n\n{body}\n\n{footer}";
+
+        // Act / Assert - optional sections may be left out
+        assert!(validate_template("feat: add login\n\n- JWT\n\nRefs: #1", template).is_ok());
+        assert!(validate_template("feat: add login", template).is_ok());
+    }
+
+    #[test]
+    fn test_validate_template_flags_non_conforming_message() {
+        // Arrange
+        let template = "[{component}] {summary}\n\n{body}";
+
+        // Act
+        let err = validate_template("feat: add login\n- JWT", template)
+            .unwrap_err()
+            .to_string();
+
+        // Assert - both the subject and the missing blank line are reported
+        assert!(err.contains("subject does not match the template `[{component}] {summary}`"));
+        assert!(err.contains("blank line"));
+    }
+
+    #[test]
+    fn test_validate_template_single_line_template() {
+        // Arrange / Act
+        let err = validate_template("fix: typo\n\nbody", "{type}: {summary}")
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert_eq!(err, "the template allows only a subject line");
+    }
+
+    #[test]
+    fn test_wrap_commit_body_wraps_long_lines() {
+        // Arrange - a plain paragraph and a numbered item
+        let message = "fix: wrap\n\n\
+                       This paragraph is far too long for a single line.\n\
+                       1. numbered items keep their hanging indent";
+
+        // Act
+        let result = wrap_commit_body(message, 20);
+
+        // Assert
+        assert_eq!(
+            result,
+            "fix: wrap\n\n\
+             This paragraph is\nfar too long for a\nsingle line.\n\
+             1. numbered items\n   keep their\n   hanging indent"
+        );
+    }
+
+    #[test]
+    fn test_wrap_commit_body_preserves_subject_and_blank_lines() {
+        // Arrange - the subject is longer than the width
+        let message = "feat: a subject longer than the wrap width\n\nshort\n\nalso short\n";
+
+        // Act
+        let result = wrap_commit_body(message, 12);
+
+        // Assert - unchanged
+        assert_eq!(result, message);
+    }
+
+    #[test]
+    fn test_wrap_commit_body_skips_leading_blank_lines() {
+        // Arrange - an untrimmed response (`trim_output = "trim_end"`)
+        let message = "\n\nfeat: a subject longer than the wrap width\n\nbody text here";
+
+        // Act
+        let result = wrap_commit_body(message, 12);
+
+        // Assert - the subject stays whole; only the body is wrapped
+        assert_eq!(
+            result,
+            "\n\nfeat: a subject longer than the wrap width\n\nbody text\nhere"
+        );
+    }
+
+    #[test]
+    fn test_wrap_commit_body_skips_code_fences() {
+        // Arrange
+        let message = "docs: add example\n\n\
+                       ```\n\
+                       let value = compute_something_long(argument_one, argument_two);\n\
+                       ```\n\
+                       Text after the fence is wrapped again.";
+
+        // Act
+        let result = wrap_commit_body(message, 20);
+
+        // Assert
+        assert_eq!(
+            result,
+            "docs: add example\n\n\
+             ```\n\
+             let value = compute_something_long(argument_one, argument_two);\n\
+             ```\n\
+             Text after the fence\nis wrapped again."
+        );
+    }
+
+    #[test]
+    fn test_wrap_commit_body_keeps_long_words() {
+        // Arrange / Act
+        let result = wrap_commit_body("fix: x\n\nsee https://example.com/a/very/long/url", 10);
+
+        // Assert - the URL is not split
+        assert_eq!(result, "fix: x\n\nsee\nhttps://example.com/a/very/long/url");
+    }
+
+    #[test]
+    fn test_validate_allowed_type_accepts_listed_types() {
+        // Arrange
+        let allowed = vec!["feat".to_string(), "fix".to_string(), "docs".to_string()];
+
+        // Act / Assert - scope and breaking marker do not affect the type
+        assert!(validate_allowed_type("feat: add login", &allowed).is_ok());
+        assert!(validate_allowed_type("fix(parser)!: reject empty input", &allowed).is_ok());
+        assert!(validate_allowed_type("docs!: rewrite README\n\nbody", &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_validate_allowed_type_rejects_other_types() {
+        // Arrange
+        let allowed = vec!["feat".to_string(), "fix".to_string()];
+
+        // Act
+        let other = validate_allowed_type("chore(deps): bump tokio", &allowed)
+            .unwrap_err()
+            .to_string();
+        let missing = validate_allowed_type("Bump tokio", &allowed)
+            .unwrap_err()
+            .to_string();
+
+        // Assert
+        assert_eq!(other, "type 'chore' is not allowed (allowed: feat, fix)");
+        assert!(missing.contains("no commit type"));
+    }
+
+    #[test]
+    fn test_validate_allowed_type_empty_list_accepts_anything() {
+        // Arrange / Act / Assert
+        assert!(validate_allowed_type("Whatever", &[]).is_ok());
+    }
+}
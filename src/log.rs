@@ -0,0 +1,268 @@
+//! Verbosity-controlled status messages
+//!
+//! Every line written to stderr goes through this module. Informational and
+//! debug lines use [`info`] and [`debug`] so that `--quiet` and `--verbose`
+//! apply consistently; [`warn`] and [`error`] are always printed.
+//!
+//! With `--log-format json` each line is a JSON object with a timestamp, the
+//! level, the message and any structured fields, for CI log collectors.
+//! Logs always go to stderr so they never mix with the generated message.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::SystemTime;
+
+use serde_json::{Map, Value, json};
+
+use crate::color;
+use crate::output::format_rfc3339;
+
+/// How much status output to print
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only warnings and errors
+    Quiet,
+    /// Progress messages such as "Launching git commit editor..." (default)
+    #[default]
+    Normal,
+    /// Also details such as the config path, diff size and model
+    Verbose,
+}
+
+impl Verbosity {
+    /// Verbosity selected by the `--quiet` / `--verbose` flags
+    pub fn from_flags(quiet: bool, verbose: bool) -> Self {
+        if quiet {
+            Verbosity::Quiet
+        } else if verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+/// How status lines are written to stderr
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Plain text, as shown in a terminal (default)
+    #[default]
+    Text,
+    /// One JSON object per line: `{"timestamp", "level", "message", "fields"}`
+    Json,
+}
+
+/// Severity of a log line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// Name used in JSON logs
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    /// Lowest verbosity at which lines of this level are printed
+    fn verbosity(self) -> Verbosity {
+        match self {
+            Level::Error | Level::Warn => Verbosity::Quiet,
+            Level::Info => Verbosity::Normal,
+            Level::Debug => Verbosity::Verbose,
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Set the verbosity for the rest of the process
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Current verbosity
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+
+/// Set the log format for the rest of the process
+pub fn set_format(format: LogFormat) {
+    JSON_FORMAT.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+/// Current log format
+pub fn format() -> LogFormat {
+    if JSON_FORMAT.load(Ordering::Relaxed) {
+        LogFormat::Json
+    } else {
+        LogFormat::Text
+    }
+}
+
+/// Print an error to stderr, prefixed with `Error:` in text logs
+pub fn error(message: impl Display) {
+    emit(Level::Error, message, &[]);
+}
+
+/// Print a warning to stderr, prefixed with `Warning:` in text logs
+pub fn warn(message: impl Display) {
+    emit(Level::Warn, message, &[]);
+}
+
+/// Print an informational message to stderr unless `--quiet` is set
+pub fn info(message: impl Display) {
+    emit(Level::Info, message, &[]);
+}
+
+/// Print a detail message to stderr only with `--verbose`
+pub fn debug(message: impl Display) {
+    emit(Level::Debug, message, &[]);
+}
+
+/// Print a detail message with structured fields to stderr only with `--verbose`
+///
+/// The fields appear only in JSON logs; text logs show just the message.
+pub fn debug_fields(message: impl Display, fields: &[(&str, Value)]) {
+    emit(Level::Debug, message, fields);
+}
+
+/// Write a log line at `level` if the current verbosity includes it
+fn emit(level: Level, message: impl Display, fields: &[(&str, Value)]) {
+    if verbosity() < level.verbosity() {
+        return;
+    }
+    match format() {
+        LogFormat::Text => eprintln!("{}", text_line(level, &message.to_string())),
+        LogFormat::Json => eprintln!(
+            "{}",
+            json_line(SystemTime::now(), level, &message.to_string(), fields)
+        ),
+    }
+}
+
+/// Render one text log line
+///
+/// Warnings and errors get a colored prefix. With color enabled (a terminal),
+/// they also clear the current line first so a running spinner does not
+/// garble them.
+fn text_line(level: Level, message: &str) -> String {
+    let prefix = match level {
+        Level::Error => color::error("Error:"),
+        Level::Warn => color::warning("Warning:"),
+        Level::Info | Level::Debug => return message.to_string(),
+    };
+    let clear = if color::enabled() { "\r\x1b[K" } else { "" };
+    format!("{}{} {}", clear, prefix, message)
+}
+
+/// Render one JSON log line
+fn json_line(time: SystemTime, level: Level, message: &str, fields: &[(&str, Value)]) -> String {
+    let fields: Map<String, Value> = fields
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    json!({
+        "timestamp": format_rfc3339(time),
+        "level": level.as_str(),
+        "message": message.trim_end(),
+        "fields": fields,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_from_flags() {
+        // Arrange / Act / Assert
+        assert_eq!(Verbosity::from_flags(false, false), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(true, false), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, true), Verbosity::Verbose);
+    }
+
+    #[test]
+    fn test_json_line_has_timestamp_and_fields() {
+        // Arrange
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+
+        // Act
+        let line = json_line(
+            time,
+            Level::Debug,
+            "Diff size: 42 bytes\n",
+            &[("diff_bytes", json!(42))],
+        );
+
+        // Assert - a single line that parses back to the same entry
+        assert!(!line.contains('\n'));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "timestamp": "2023-11-14T22:13:20Z",
+                "level": "debug",
+                "message": "Diff size: 42 bytes",
+                "fields": {"diff_bytes": 42},
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_line_without_fields() {
+        // Arrange / Act
+        let line = json_line(std::time::UNIX_EPOCH, Level::Info, "Staged 2 file(s)", &[]);
+
+        // Assert
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "info");
+        assert_eq!(value["fields"], json!({}));
+    }
+
+    #[test]
+    fn test_json_line_warning_has_no_text_prefix() {
+        // Arrange / Act
+        let line = json_line(
+            std::time::UNIX_EPOCH,
+            Level::Warn,
+            "diff truncated from 10 to 5 bytes",
+            &[],
+        );
+
+        // Assert
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "warn");
+        assert_eq!(value["message"], "diff truncated from 10 to 5 bytes");
+    }
+
+    #[test]
+    fn test_warnings_and_errors_print_when_quiet() {
+        // Arrange / Act / Assert
+        assert_eq!(Level::Error.verbosity(), Verbosity::Quiet);
+        assert_eq!(Level::Warn.verbosity(), Verbosity::Quiet);
+        assert_eq!(Level::Info.verbosity(), Verbosity::Normal);
+    }
+
+    #[test]
+    fn test_verbosity_ordering() {
+        // Arrange / Act / Assert - each level includes the ones below it
+        assert!(Verbosity::Quiet < Verbosity::Normal);
+        assert!(Verbosity::Normal < Verbosity::Verbose);
+    }
+}
@@ -3,15 +3,36 @@
 //! This tool analyzes staged git changes and uses Claude to generate
 //! appropriate commit messages in conventional commits format.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
+use serde::Serialize;
+use serde_json::json;
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
 
 use claude_commit::{
-    claude::generate_message,
-    cli::{Args, Commands, find_config_file, run_init},
-    config::load_config,
-    git::{get_git_diff, run_pre_commit_hook},
-    output::CommitMessage,
+    Error,
+    claude::{diff_for_prompt, generate_candidates, generate_message_with_retries, prepare_prompt},
+    cli::{Args, Commands, check_config, run_init, run_install_hook, write_regenerated},
+    clipboard::{copy_to_clipboard, ensure_available},
+    color,
+    config::{Config, load_configs, resolve_config_paths},
+    git::{
+        CommitOptions, DiffOptions, detect_special_state, find_conflict_markers, get_amend_base,
+        get_file_diff, get_git_diff, get_ignore_patterns, resolve_since,
+        run_git_amend_keep_message, run_git_commit_direct, run_pre_commit_hook, stage_all,
+        write_commit_message,
+    },
+    log::{self, LogFormat, Verbosity},
+    output::{
+        CommitMessage, CommitMessages, ErrorOutput, OutputFormat, PromptOutput, PromptSizeOutput,
+        format_rfc3339, output_schema, write_output_file,
+    },
+    pipeline::{
+        build_prompt_context, check_diff_file_count, empty_diff_message, normalize_external_diff,
+    },
+    prompt::{PromptContext, calculate_prompt_size},
     ui::interactive_commit,
 };
 
@@ -21,66 +42,448 @@ use claude_commit::{
 ///
 /// 1. Parse command-line arguments
 /// 2. Resolve configuration file (explicit path or auto-search)
-/// 3. Get git diff from staging area
-/// 4. Run pre-commit hook (skip if not present)
+///    With `--amend-keep-message`, amend HEAD keeping its message, then exit
+/// 3. Abort if a merge or rebase is in progress and a commit is being
+///    prepared (unless `--force`),
+///    then stage all changes with `--stage-all` and get git diff
+///    (staging area by default, see `--diff-mode`, `--range` and `--file`),
+///    or read it from `--diff-file` / `--diff-stdin` and skip steps 4-5
+/// 4. Run pre-commit hook (skip if not present or with `--no-verify`)
 /// 5. Re-fetch git diff (reflect formatter auto-fixes)
-/// 6. JSON mode: generate message and print, then exit
+/// 6. Abort if added lines contain merge-conflict markers
+///    (unless `--allow-conflict-markers`)
+/// 7. Print-size mode: print the prompt size against `max_prompt_size`, then exit
+///    Dry-run mode: print the prompt (or `{"prompt": ...}` in JSON mode), then exit
+///    Regenerate subcommand: generate message, overwrite the message file and
+///    print how the message changed
+///    JSON mode: generate message and print, then exit
+///    No-edit mode: generate message and commit with `git commit -F`
+///    Write-message mode: generate message and write it to the given file
+///    Clipboard mode: generate message and copy it to the clipboard
+///    (`--clipboard` also copies the message in the modes above)
 ///    Interactive mode: generate with spinner → [A]ccept / [E]dit / [R]egenerate / [Q]uit
+///
+/// Errors are reported through [`log::error`], so `--log-format json` also
+/// covers them.
 #[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+async fn main() {
+    if let Err(e) = run().await {
+        log::error(format!("{:#}", e));
+        std::process::exit(1);
+    }
+}
+
+/// Run the CLI; see [`main`] for the process flow
+async fn run() -> Result<()> {
+    let mut args = Args::parse();
+    let output_format = args.output_format();
+    color::set_enabled(color::should_enable(
+        args.no_color,
+        output_format.is_some() || args.log_format == LogFormat::Json,
+        std::env::var_os(color::NO_COLOR_ENV).as_deref(),
+        std::io::stdout().is_terminal(),
+    ));
+    log::set_verbosity(Verbosity::from_flags(args.quiet, args.verbose));
+    log::set_format(args.log_format);
+    args.check_regenerate_flags()?;
 
     // Handle subcommands
     if let Some(Commands::Init { output, force }) = args.command {
         return run_init(output.as_deref(), force);
     }
+    if args.print_schema {
+        println!("{}", OutputFormat::JsonPretty.serialize(&output_schema())?);
+        return Ok(());
+    }
+    // Check before calling Claude so a build without the feature fails fast
+    if args.clipboard {
+        ensure_available()?;
+    }
 
-    // Resolve config file path
-    let config_path = match args.config {
-        Some(path) => path,
-        None => match find_config_file() {
-            Some(path) => path.to_string_lossy().to_string(),
-            None => {
-                eprintln!("Error: No configuration file found.");
-                eprintln!("Searched locations:");
-                eprintln!("  ~/.config/claude_commit/config.toml");
-                eprintln!("  <git root>/.claude_commit.toml");
-                eprintln!("  ./.claude_commit.toml");
-                eprintln!();
-                eprintln!("Run 'claude_commit init' to create a config file.");
+    // An explicit --config skips the search, including the repository's shared config
+    let config_paths = resolve_config_paths(std::mem::take(&mut args.config))?;
+
+    if let Some(Commands::CheckConfig) = args.command {
+        match check_config(&config_paths, args.profile.as_deref()) {
+            Ok(report) => {
+                println!("{}", color::success(&report));
+                return Ok(());
+            }
+            Err(e) => {
+                log::error(format!("{:#}", e));
                 std::process::exit(1);
             }
+        }
+    }
+
+    let mut config = load_configs(&config_paths, args.profile.as_deref())?;
+    if let Some(Commands::InstallHook { force }) = args.command {
+        let [config_path] = config_paths.as_slice() else {
+            anyhow::bail!(
+                "install-hook requires exactly one config file; create one with `claude_commit init`"
+            );
+        };
+        return run_install_hook(Path::new(config_path), force);
+    }
+
+    // Amend without generating a message: Claude is never called in this mode
+    if args.amend_keep_message {
+        if args.stage_all {
+            let staged = stage_all()?;
+            log::info(color::success(&format!("Staged {} file(s)", staged)));
+        }
+        return Ok(run_git_amend_keep_message(&CommitOptions {
+            amend: true,
+            sign: args.sign || config.sign,
+            no_verify: args.no_verify,
+            only: args.only,
+            ..CommitOptions::default()
+        })?);
+    }
+
+    args.apply_config_overrides(&mut config);
+    config.use_cache = !args.no_cache && config.cache_ttl_secs > 0;
+    config.stream = args.stream;
+
+    // Regenerate asks Claude for a fresh take on the same diff
+    let regenerate = matches!(args.command, Some(Commands::Regenerate));
+    if regenerate {
+        config.use_cache = false;
+    }
+
+    // When amending, describe the whole amended commit: HEAD's changes plus staged ones
+    let mut diff_options = DiffOptions {
+        mode: args.diff_mode,
+        range: args.range.clone(),
+        revision: if args.amend {
+            Some(get_amend_base()?)
+        } else {
+            args.since.as_deref().map(resolve_since).transpose()?
+        },
+        // With --file the stat covers that file too
+        pathspecs: if let Some(file) = &args.file {
+            vec![file.clone()]
+        } else if args.only.is_empty() {
+            args.paths
+        } else {
+            args.only.clone()
         },
+        ..DiffOptions::from_config(&config)
+    };
+    let commit_options = CommitOptions {
+        only: args.only,
+        amend: args.amend,
+        write_editmsg: args.write_editmsg,
+        sign: args.sign || config.sign,
+        no_verify: args.no_verify,
     };
 
-    let config = load_config(&config_path)?;
+    let external_diff = read_external_diff(args.diff_file.as_deref(), args.diff_stdin)?;
+    let from_git = external_diff.is_none();
+    if from_git {
+        diff_options.exclude_paths.extend(get_ignore_patterns()?);
+    }
 
-    // Get staged changes
-    let diff = get_git_diff()?;
-    if diff.trim().is_empty() {
-        eprintln!("Error: No staged changes found.");
-        eprintln!("Please stage your changes with 'git add' before generating a commit message.");
-        std::process::exit(1);
+    // Committing mid-merge or mid-rebase gives confusing results; previews and
+    // output that is never committed (--range, --json) are harmless.
+    // Regenerate still prepares a commit, so it stays guarded.
+    let prepares_commit = regenerate || (output_format.is_none() && args.range.is_none());
+    if from_git
+        && !args.force
+        && !args.dry_run
+        && !args.print_size
+        && prepares_commit
+        && let Some(state) = detect_special_state()?
+    {
+        exit_with_error(
+            output_format,
+            &format!(
+                "A {} is in progress. Finish or abort it first, \
+                 or pass --force to continue anyway.",
+                state
+            ),
+        );
     }
 
-    // Run pre-commit hook before calling Claude API
-    run_pre_commit_hook()?;
+    // With --file, only that file's staged diff is described
+    let fetch_diff = || -> Result<String> {
+        match args.file.as_deref() {
+            Some(path) => match get_file_diff(path) {
+                Ok(diff) => Ok(normalize_external_diff(&diff, &config)?),
+                Err(Error::NoChanges(message)) => exit_with_error(output_format, &message),
+                Err(e) => Err(e.into()),
+            },
+            None => Ok(get_git_diff(&diff_options)?),
+        }
+    };
 
-    // Re-fetch diff to reflect any auto-fixes by formatters
-    let diff = get_git_diff()?;
-    if diff.trim().is_empty() {
-        eprintln!("Error: No staged changes remain after pre-commit hook.");
-        eprintln!("The pre-commit hook may have unstaged all changes.");
-        std::process::exit(1);
+    let diff = match external_diff {
+        Some(diff) => {
+            let diff = normalize_external_diff(&diff, &config)?;
+            if diff.is_empty() {
+                exit_with_error(output_format, "The provided diff is empty.");
+            }
+            diff
+        }
+        None => {
+            if args.stage_all {
+                let staged = stage_all()?;
+                log::info(color::success(&format!("Staged {} file(s)", staged)));
+            }
+
+            // Get changes for the selected diff mode
+            let diff = fetch_diff()?;
+            if diff.trim().is_empty() {
+                let message = match &diff_options.range {
+                    Some(range) => format!("No changes found in range {}.", range),
+                    None => empty_diff_message(diff_options.mode).to_string(),
+                };
+                exit_with_error(output_format, &message);
+            }
+
+            // Run pre-commit hook before calling Claude API.
+            // From the prepare-commit-msg hook, git has already run it;
+            // a dry run must not touch the working tree, a range diff
+            // does not depend on it, regenerate is meant to be cheap to
+            // repeat, and --no-verify skips hooks entirely.
+            if args.write_message.is_none()
+                && !regenerate
+                && !args.dry_run
+                && !args.print_size
+                && args.range.is_none()
+                && !args.no_verify
+            {
+                run_pre_commit_hook()?;
+            }
+
+            // Re-fetch diff to reflect any auto-fixes by formatters
+            let diff = fetch_diff()?;
+            if diff.trim().is_empty() {
+                exit_with_error(
+                    output_format,
+                    "No changes remain after pre-commit hook. \
+                     The pre-commit hook may have unstaged all changes.",
+                );
+            }
+            diff
+        }
+    };
+
+    // Committing unresolved conflicts is almost always a mistake
+    if !args.allow_conflict_markers {
+        let files = find_conflict_markers(&diff);
+        if !files.is_empty() {
+            exit_with_error(
+                output_format,
+                &format!(
+                    "Merge-conflict markers found in: {}. \
+                     Resolve the conflicts, or pass --allow-conflict-markers to continue anyway.",
+                    files.join(", ")
+                ),
+            );
+        }
     }
 
-    if args.json {
-        let message = generate_message(&diff, &config).await?;
-        let output = CommitMessage { message };
-        println!("{}", serde_json::to_string(&output)?);
+    check_diff_file_count(&diff, config.max_diff_files)?;
+    let context = build_prompt_context(&config, from_git.then_some(&diff_options))?;
+
+    log::debug_fields(
+        format!(
+            "Config: {}",
+            if config_paths.is_empty() {
+                "(environment variables)".to_string()
+            } else {
+                config_paths.join(", ")
+            }
+        ),
+        &[("config_paths", json!(config_paths))],
+    );
+    log::debug_fields(
+        format!("Diff size: {} bytes", diff.len()),
+        &[("diff_bytes", json!(diff.len()))],
+    );
+    log::debug_fields(
+        format!(
+            "Model: {}",
+            config.model.as_deref().unwrap_or("(claude default)")
+        ),
+        &[("model", json!(config.model))],
+    );
+
+    if args.print_size {
+        // Same measure as the limit check: the system prompt counts toward it
+        let prompt_size = calculate_prompt_size(&diff, &config.prompt, &context)
+            + config.system_prompt.as_deref().map_or(0, str::len);
+        let max = config.max_prompt_size;
+        match output_format {
+            Some(format) => emit_json(
+                format,
+                args.output.as_deref(),
+                &PromptSizeOutput { prompt_size, max },
+            )?,
+            None => println!(
+                "Prompt size: {} bytes ({}% of max_prompt_size {} bytes)",
+                prompt_size,
+                prompt_size * 100 / max,
+                max
+            ),
+        }
+        return Ok(());
+    }
+
+    if args.dry_run {
+        let prompt = prepare_prompt(&diff, &config, &context)?;
+        let system_prompt = config.system_prompt.clone();
+        match (output_format, system_prompt) {
+            (Some(format), system_prompt) => {
+                let output = PromptOutput {
+                    prompt,
+                    system_prompt,
+                };
+                emit_json(format, args.output.as_deref(), &output)?;
+            }
+            (None, Some(system_prompt)) => {
+                println!("[system prompt]\n{}\n\n[prompt]\n{}", system_prompt, prompt)
+            }
+            (None, None) => println!("{}", prompt),
+        }
+        return Ok(());
+    }
+
+    if regenerate {
+        let message = generate_timed(&diff, &config, &context).await?;
+        return write_regenerated(&message, config.message_file.as_deref());
+    }
+
+    if let Some(format) = output_format {
+        if config.candidates > 1 {
+            let started = Instant::now();
+            let messages = generate_candidates(&diff, &config, &context, config.candidates).await?;
+            log_generated(started);
+            if args.clipboard
+                && let Some(first) = messages.first()
+            {
+                copy_message(first)?;
+            }
+            emit_json(format, args.output.as_deref(), &CommitMessages { messages })?;
+            return Ok(());
+        }
+
+        let message = generate_timed(&diff, &config, &context).await?;
+        if args.clipboard {
+            copy_message(&message)?;
+        }
+        let (sent, truncated) = diff_for_prompt(&diff, &config, &context);
+        let output = CommitMessage {
+            message,
+            model: config.model.clone(),
+            generated_at: Some(format_rfc3339(SystemTime::now())),
+            diff_bytes: Some(sent.len()),
+            truncated,
+            original_diff_bytes: truncated.then_some(diff.len()),
+        };
+        emit_json(format, args.output.as_deref(), &output)?;
+    } else if let Some(path) = args.write_message.as_deref() {
+        let message = generate_timed(&diff, &config, &context).await?;
+        if args.clipboard {
+            copy_message(&message)?;
+        }
+        write_commit_message(&message, Some(path))?;
+    } else if args.no_edit {
+        let message = generate_timed(&diff, &config, &context).await?;
+        if args.clipboard {
+            copy_message(&message)?;
+        }
+        let msg_file = write_commit_message(&message, config.message_file.as_deref())?;
+        run_git_commit_direct(&msg_file, &commit_options)?;
+    } else if args.clipboard {
+        let message = generate_timed(&diff, &config, &context).await?;
+        copy_message(&message)?;
+        println!("{}", message);
     } else {
-        interactive_commit(&diff, &config).await?;
+        interactive_commit(&diff, &config, &context, &commit_options).await?;
     }
 
     Ok(())
 }
+
+/// Generate a message with the configured retries and log how long it took
+async fn generate_timed(diff: &str, config: &Config, context: &PromptContext) -> Result<String> {
+    let started = Instant::now();
+    let message = generate_message_with_retries(
+        diff,
+        config,
+        context,
+        config.retries,
+        Duration::from_millis(config.retry_delay_ms),
+    )
+    .await?;
+    log_generated(started);
+    Ok(message)
+}
+
+/// Log the time spent generating since `started`
+fn log_generated(started: Instant) {
+    let elapsed = started.elapsed();
+    log::debug_fields(
+        format!("Generated in {:.1}s", elapsed.as_secs_f64()),
+        &[("duration_ms", json!(elapsed.as_millis()))],
+    );
+}
+
+/// Copy a generated message to the clipboard and report it
+fn copy_message(message: &str) -> Result<()> {
+    copy_to_clipboard(message)?;
+    log::info(color::success("Copied the message to the clipboard"));
+    Ok(())
+}
+
+/// Print a JSON document, or write it to the `--output` file and report the path
+fn emit_json<T: Serialize>(format: OutputFormat, output: Option<&str>, value: &T) -> Result<()> {
+    let json = format.serialize(value)?;
+    match output {
+        Some(path) => {
+            write_output_file(Path::new(path), &json)?;
+            log::info(color::success(&format!("Wrote {}", path)));
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Read a diff supplied via `--diff-file` or `--diff-stdin`
+///
+/// Returns `None` when the diff should be taken from git.
+fn read_external_diff(diff_file: Option<&str>, diff_stdin: bool) -> Result<Option<String>> {
+    if let Some(path) = diff_file {
+        let diff = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read diff file: {}", path))?;
+        return Ok(Some(diff));
+    }
+    if diff_stdin {
+        let mut diff = String::new();
+        std::io::stdin()
+            .read_to_string(&mut diff)
+            .context("Failed to read diff from stdin")?;
+        return Ok(Some(diff));
+    }
+    Ok(None)
+}
+
+/// Report an error and exit with a non-zero status
+///
+/// In JSON mode the error is printed to stdout as `{"error":"..."}` so that
+/// callers parsing the output never mistake it for an empty message.
+fn exit_with_error(format: Option<OutputFormat>, message: &str) -> ! {
+    if let Some(format) = format {
+        let output = ErrorOutput {
+            error: message.to_string(),
+        };
+        if let Ok(serialized) = format.serialize(&output) {
+            println!("{}", serialized);
+        }
+    } else {
+        log::error(message);
+    }
+    std::process::exit(1);
+}
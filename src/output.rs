@@ -1,12 +1,87 @@
-//! Output structures for JSON formatting
+//! Output structures for JSON and TOML formatting
 //!
 //! This module provides structures for serializing commit messages
-//! into JSON format for programmatic consumption.
+//! into JSON (or TOML) format for programmatic consumption.
 
+use schemars::{JsonSchema, Schema, schema_for};
 use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+/// Serialization format for machine-readable output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Single-line JSON for machine consumption
+    Json,
+    /// Indented, multi-line JSON for human review
+    JsonPretty,
+    /// TOML document; multi-line messages use `"""` strings
+    Toml,
+}
+
+impl OutputFormat {
+    /// Serialize a value in this format
+    ///
+    /// # Errors
+    ///
+    /// * The value cannot be represented in the format
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String> {
+        match self {
+            OutputFormat::Json => serde_json::to_string(value).map_err(json_error),
+            OutputFormat::JsonPretty => serde_json::to_string_pretty(value).map_err(json_error),
+            OutputFormat::Toml => to_toml(value),
+        }
+    }
+}
+
+/// Wrap a JSON serialization error
+fn json_error(source: serde_json::Error) -> Error {
+    Error::Serialize {
+        format: "JSON",
+        source: Box::new(source),
+    }
+}
+
+/// Serialize an output structure as a TOML document
+///
+/// Strings containing newlines are written as multi-line `"""` strings, and
+/// the trailing newline of the document is removed to match the JSON output.
+///
+/// # Errors
+///
+/// * The value is not a table (TOML documents must be)
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::output::{CommitMessage, to_toml};
+///
+/// let commit = CommitMessage {
+///     message: "feat: add login\n\n- OAuth support".to_string(),
+///     ..Default::default()
+/// };
+///
+/// let toml = to_toml(&commit).unwrap();
+/// assert_eq!(toml, "message = \"\"\"\nfeat: add login\n\n- OAuth support\"\"\"");
+/// ```
+pub fn to_toml<T: Serialize>(value: &T) -> Result<String> {
+    toml::to_string(value)
+        .map(|toml| toml.trim_end().to_string())
+        .map_err(|e| Error::Serialize {
+            format: "TOML",
+            source: Box::new(e),
+        })
+}
 
 /// Commit message structure for JSON output
 ///
+/// Metadata fields are omitted from the JSON when `None` (and `truncated`
+/// when `false`), so consumers of the plain `{"message": ...}` shape keep
+/// working.
+///
 /// # Example
 ///
 /// ```
@@ -15,25 +90,234 @@ use serde::Serialize;
 ///
 /// let commit = CommitMessage {
 ///     message: "feat: add new feature".to_string(),
+///     ..Default::default()
 /// };
 ///
 /// let json = serde_json::to_string(&commit).unwrap();
 /// assert_eq!(json, r#"{"message":"feat: add new feature"}"#);
 /// ```
-#[derive(Serialize)]
+#[derive(Default, Serialize, JsonSchema)]
+// Doc comments contain doctests, which do not belong in the schema
+#[schemars(description = "A generated commit message with optional generation metadata")]
 pub struct CommitMessage {
     /// The generated commit message content
     pub message: String,
+    /// Model requested for generation (absent when the CLI default was used)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Generation time in RFC 3339 format (UTC)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    /// Size of the diff the message was generated from, in bytes
+    ///
+    /// When the diff was truncated to fit the prompt, this is the size after
+    /// truncation; see `original_diff_bytes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff_bytes: Option<usize>,
+    /// Whether the diff was truncated (`on_oversize = "truncate"`), i.e. the
+    /// message was generated from a partial diff
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Size of the diff before truncation, in bytes (only when `truncated`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_diff_bytes: Option<usize>,
+}
+
+/// Multiple candidate commit messages for JSON output
+///
+/// Printed instead of [`CommitMessage`] when more than one candidate is requested.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::output::CommitMessages;
+///
+/// let candidates = CommitMessages {
+///     messages: vec!["feat: a".to_string(), "feat: b".to_string()],
+/// };
+///
+/// let json = serde_json::to_string(&candidates).unwrap();
+/// assert_eq!(json, r#"{"messages":["feat: a","feat: b"]}"#);
+/// ```
+#[derive(Serialize, JsonSchema)]
+#[schemars(description = "Several candidate commit messages")]
+pub struct CommitMessages {
+    /// The generated candidate messages, in generation order
+    pub messages: Vec<String>,
+}
+
+/// Prompt preview for JSON output in dry-run mode
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::output::PromptOutput;
+///
+/// let preview = PromptOutput {
+///     prompt: "Generate:\n\n+added".to_string(),
+///     system_prompt: None,
+/// };
+///
+/// let json = serde_json::to_string(&preview).unwrap();
+/// assert_eq!(json, r#"{"prompt":"Generate:\n\n+added"}"#);
+/// ```
+#[derive(Serialize, JsonSchema)]
+#[schemars(description = "The prompt that would be sent to Claude (`--dry-run`)")]
+pub struct PromptOutput {
+    /// The full prompt that would be sent to Claude
+    pub prompt: String,
+    /// The system prompt sent alongside it, if configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+}
+
+/// Prompt size report for JSON output with `--print-size`
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::output::PromptSizeOutput;
+///
+/// let size = PromptSizeOutput {
+///     prompt_size: 1_234,
+///     max: 1_000_000,
+/// };
+///
+/// let json = serde_json::to_string(&size).unwrap();
+/// assert_eq!(json, r#"{"prompt_size":1234,"max":1000000}"#);
+/// ```
+#[derive(Serialize, JsonSchema)]
+#[schemars(description = "Size of the prompt that would be sent (`--print-size`)")]
+pub struct PromptSizeOutput {
+    /// Prompt size in bytes, including the system prompt
+    pub prompt_size: usize,
+    /// The configured `max_prompt_size`
+    pub max: usize,
+}
+
+/// Write a serialized JSON document to `path` (used by `--output`)
+///
+/// Missing parent directories are created. A trailing newline is added,
+/// matching what is printed to stdout.
+///
+/// # Errors
+///
+/// * Failed to create a parent directory or write the file
+pub fn write_output_file(path: &Path, json: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::io(format!("Failed to create {}", parent.display()), e))?;
+    }
+    fs::write(path, format!("{}\n", json))
+        .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))
+}
+
+/// Format a point in time as an RFC 3339 UTC timestamp (`YYYY-MM-DDTHH:MM:SSZ`)
+///
+/// Times before the Unix epoch are clamped to the epoch.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::output::format_rfc3339;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// assert_eq!(format_rfc3339(time), "2023-11-14T22:13:20Z");
+/// ```
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = secs / 86_400;
+    let secs_of_day = secs % 86_400;
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Error structure for JSON output
+///
+/// Printed instead of [`CommitMessage`] when no message could be generated.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::output::ErrorOutput;
+///
+/// let error = ErrorOutput {
+///     error: "No staged changes found.".to_string(),
+/// };
+///
+/// let json = serde_json::to_string(&error).unwrap();
+/// assert_eq!(json, r#"{"error":"No staged changes found."}"#);
+/// ```
+#[derive(Serialize, JsonSchema)]
+#[schemars(description = "Why no message could be generated")]
+pub struct ErrorOutput {
+    /// Human-readable description of the failure
+    pub error: String,
+}
+
+/// Any document printed in JSON mode
+///
+/// Only used to describe the output contract; see [`output_schema`].
+#[derive(Serialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(
+    title = "claude_commit JSON output",
+    description = "Any document printed by claude_commit in JSON mode"
+)]
+pub enum JsonOutput {
+    /// A single generated message with optional metadata
+    Message(CommitMessage),
+    /// Several candidate messages (`candidates` > 1)
+    Candidates(CommitMessages),
+    /// The prompt preview printed with `--dry-run`
+    Prompt(PromptOutput),
+    /// The prompt size printed with `--print-size`
+    PromptSize(PromptSizeOutput),
+    /// The failure reported instead of a message
+    Error(ErrorOutput),
+}
+
+/// JSON Schema describing every document printed in JSON mode
+///
+/// Printed by `--print-schema` so tools can validate the output or generate
+/// bindings. Metadata fields that may be omitted are not `required`.
+pub fn output_schema() -> Schema {
+    schema_for!(JsonOutput)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
     #[test]
     fn test_commit_message_serialize_basic() {
         // Arrange - basic commit message
         let commit = CommitMessage {
             message: "feat: add new feature".to_string(),
+            ..Default::default()
         };
 
         // Act
@@ -50,6 +334,7 @@ mod tests {
         // Arrange - message with special characters
         let commit = CommitMessage {
             message: r#"fix: resolve "quote" issue and \backslash"#.to_string(),
+            ..Default::default()
         };
 
         // Act
@@ -68,6 +353,7 @@ mod tests {
         // Arrange - empty message
         let commit = CommitMessage {
             message: "".to_string(),
+            ..Default::default()
         };
 
         // Act
@@ -85,6 +371,7 @@ mod tests {
         let commit = CommitMessage {
             message: "feat: add feature\n\nThis is a longer description.\nWith multiple lines."
                 .to_string(),
+            ..Default::default()
         };
 
         // Act
@@ -103,6 +390,7 @@ mod tests {
         // Arrange - message with Unicode and emoji
         let commit = CommitMessage {
             message: "feat: 日本語サポート追加 🎉🚀".to_string(),
+            ..Default::default()
         };
 
         // Act
@@ -122,6 +410,7 @@ mod tests {
         // Arrange - serialize a message first
         let original = CommitMessage {
             message: "test: verify roundtrip".to_string(),
+            ..Default::default()
         };
         let json = serde_json::to_string(&original).unwrap();
 
@@ -132,4 +421,213 @@ mod tests {
         assert!(parsed.is_object());
         assert_eq!(parsed["message"], "test: verify roundtrip");
     }
+
+    #[test]
+    fn test_write_output_file_creates_parent_dirs() {
+        // Arrange
+        let dir = TempDir::new("output");
+        let path = dir.join("ci/result.json");
+        let original = CommitMessage {
+            message: "feat: add output flag\n\n- CI friendly".to_string(),
+            diff_bytes: Some(12),
+            ..Default::default()
+        };
+        let json = OutputFormat::JsonPretty.serialize(&original).unwrap();
+
+        // Act
+        write_output_file(&path, &json).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+
+        // Assert - the file parses back to the original message
+        let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed["message"], original.message);
+        assert_eq!(parsed["diff_bytes"], 12);
+    }
+
+    #[test]
+    fn test_error_output_serialize() {
+        // Arrange - error with backticks and quotes
+        let error = ErrorOutput {
+            error: "No staged changes found. Stage files with `git add` first.".to_string(),
+        };
+
+        // Act
+        let json = serde_json::to_string(&error).unwrap();
+
+        // Assert - only the error field is present
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            parsed["error"],
+            "No staged changes found. Stage files with `git add` first."
+        );
+        assert!(parsed.get("message").is_none());
+    }
+
+    #[test]
+    fn test_commit_message_serialize_with_metadata() {
+        // Arrange - all metadata fields populated
+        let commit = CommitMessage {
+            message: "feat: add metadata".to_string(),
+            model: Some("sonnet".to_string()),
+            generated_at: Some("2026-01-02T03:04:05Z".to_string()),
+            diff_bytes: Some(1234),
+            ..Default::default()
+        };
+
+        // Act
+        let json = serde_json::to_string(&commit).unwrap();
+
+        // Assert - full shape, message first
+        assert_eq!(
+            json,
+            r#"{"message":"feat: add metadata","model":"sonnet","generated_at":"2026-01-02T03:04:05Z","diff_bytes":1234}"#
+        );
+    }
+
+    #[test]
+    fn test_commit_message_serialize_truncated() {
+        // Arrange - the message was generated from a truncated diff
+        let commit = CommitMessage {
+            message: "feat: add metadata".to_string(),
+            diff_bytes: Some(1000),
+            truncated: true,
+            original_diff_bytes: Some(50_000),
+            ..Default::default()
+        };
+
+        // Act
+        let json = serde_json::to_string(&commit).unwrap();
+
+        // Assert - both sizes are reported
+        assert_eq!(
+            json,
+            r#"{"message":"feat: add metadata","diff_bytes":1000,"truncated":true,"original_diff_bytes":50000}"#
+        );
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        // Arrange / Act / Assert - epoch, leap day and end of year
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH + std::time::Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH + std::time::Duration::from_secs(1_767_225_599)),
+            "2025-12-31T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn test_output_format_json_is_compact() {
+        // Arrange
+        let commit = CommitMessage {
+            message: "feat: add flag".to_string(),
+            ..Default::default()
+        };
+
+        // Act
+        let json = OutputFormat::Json.serialize(&commit).unwrap();
+
+        // Assert - single line for machine consumption
+        assert_eq!(json, r#"{"message":"feat: add flag"}"#);
+    }
+
+    #[test]
+    fn test_output_format_json_pretty_is_indented() {
+        // Arrange
+        let commit = CommitMessage {
+            message: "feat: add flag".to_string(),
+            diff_bytes: Some(42),
+            ..Default::default()
+        };
+
+        // Act
+        let json = OutputFormat::JsonPretty.serialize(&commit).unwrap();
+
+        // Assert - one field per indented line, still valid JSON
+        assert_eq!(
+            json,
+            "{\n  \"message\": \"feat: add flag\",\n  \"diff_bytes\": 42\n}"
+        );
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["message"], "feat: add flag");
+    }
+
+    #[test]
+    fn test_commit_messages_serialize_array() {
+        // Arrange - three candidates including a multiline one
+        let candidates = CommitMessages {
+            messages: vec![
+                "feat: add login".to_string(),
+                "feat(auth): add login endpoint".to_string(),
+                "feat: add login\n\n- JWT based".to_string(),
+            ],
+        };
+
+        // Act
+        let json = serde_json::to_string(&candidates).unwrap();
+
+        // Assert - messages serialize as an ordered array
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let messages = parsed["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1], "feat(auth): add login endpoint");
+    }
+
+    #[test]
+    fn test_output_schema_commit_message_metadata_is_optional() {
+        // Arrange / Act
+        let schema = serde_json::to_value(schema_for!(CommitMessage)).unwrap();
+
+        // Assert - only the message is required
+        assert_eq!(schema["required"], serde_json::json!(["message"]));
+        for field in [
+            "message",
+            "model",
+            "generated_at",
+            "diff_bytes",
+            "truncated",
+            "original_diff_bytes",
+        ] {
+            assert!(schema["properties"].get(field).is_some(), "{field}");
+        }
+    }
+
+    #[test]
+    fn test_output_schema_covers_every_json_output() {
+        // Arrange / Act
+        let schema = serde_json::to_value(output_schema()).unwrap();
+
+        // Assert - one alternative per printed document
+        assert_eq!(schema["title"], "claude_commit JSON output");
+        assert_eq!(schema["anyOf"].as_array().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_toml_output_roundtrip_multiline() {
+        // Arrange
+        let commit = CommitMessage {
+            message: "feat: add TOML output\n\n- \"quoted\" text\n- trailing line\n".to_string(),
+            model: Some("sonnet".to_string()),
+            generated_at: Some("2026-01-02T03:04:05Z".to_string()),
+            diff_bytes: Some(1234),
+            ..Default::default()
+        };
+
+        // Act
+        let toml = OutputFormat::Toml.serialize(&commit).unwrap();
+        let parsed: toml::Table = toml.parse().unwrap();
+
+        // Assert - multi-line string syntax, and every field survives
+        assert!(toml.starts_with("message = \"\"\"\n"));
+        assert_eq!(parsed["message"].as_str(), Some(commit.message.as_str()));
+        assert_eq!(parsed["model"].as_str(), Some("sonnet"));
+        assert_eq!(
+            parsed["generated_at"].as_str(),
+            Some("2026-01-02T03:04:05Z")
+        );
+        assert_eq!(parsed["diff_bytes"].as_integer(), Some(1234));
+    }
 }
@@ -3,15 +3,274 @@
 //! This module handles building prompts from templates and git diffs,
 //! and ensures they are within acceptable size limits.
 
-use anyhow::Result;
+use crate::config::PromptPosition;
+use crate::error::{Error, Result};
+use std::borrow::Cow;
 
 /// Default maximum allowed prompt size in bytes (1MB)
 pub const DEFAULT_MAX_PROMPT_SIZE: usize = 1_000_000;
 
+/// Separator placed between prompt sections
+pub(crate) const SEPARATOR: &str = "\n\n";
+
+/// Placeholder in the prompt template replaced with the current branch name
+pub const BRANCH_PLACEHOLDER: &str = "{branch}";
+
+/// Opening fence placed before the diff with `fence_diff`
+const DIFF_FENCE_OPEN: &str = "```diff\n";
+
+/// Closing fence placed after the diff with `fence_diff`
+const DIFF_FENCE_CLOSE: &str = "\n```";
+
+/// Optional sections included in the prompt alongside the diff
+#[derive(Clone, Debug, Default)]
+pub struct PromptContext {
+    /// `git diff --stat` summary, placed right before the diff
+    pub stat: Option<String>,
+    /// Language to write the message in, added as an instruction after the template
+    pub language: Option<String>,
+    /// Skeleton the message must follow, added as an instruction after the language
+    pub message_template: Option<String>,
+    /// Commit types Claude may use, listed after the message template (empty for any)
+    pub allowed_types: Vec<String>,
+    /// Subjects of recent commits, listed before the template as a style reference
+    pub recent_commits: Vec<String>,
+    /// Text placed right before the diff (`None` uses a blank line)
+    pub diff_separator: Option<String>,
+    /// Wrap the diff in a ```` ```diff ```` fenced code block
+    pub fence_diff: bool,
+    /// Whether the other sections come before or after the diff
+    pub prompt_position: PromptPosition,
+    /// Current branch, substituted for [`BRANCH_PLACEHOLDER`] in the template
+    pub branch: Option<String>,
+    /// Earlier message for the same change, placed before the stat for Claude to improve on
+    pub previous_message: Option<String>,
+}
+
+impl PromptContext {
+    /// Separator placed between the last section before the diff and the diff
+    fn diff_separator(&self) -> &str {
+        self.diff_separator.as_deref().unwrap_or(SEPARATOR)
+    }
+
+    /// Bytes added around the diff by the code fence, if enabled
+    fn fence_size(&self) -> usize {
+        if self.fence_diff {
+            DIFF_FENCE_OPEN.len() + DIFF_FENCE_CLOSE.len()
+        } else {
+            0
+        }
+    }
+}
+
+/// Collect the sections before the diff in order, skipping absent optional ones
+fn leading_sections<'a>(prompt_template: &'a str, context: &'a PromptContext) -> Vec<Cow<'a, str>> {
+    let mut sections = Vec::new();
+    if !context.recent_commits.is_empty() {
+        sections.push(Cow::Owned(style_context(&context.recent_commits)));
+    }
+    sections.push(match &context.branch {
+        Some(branch) if prompt_template.contains(BRANCH_PLACEHOLDER) => {
+            Cow::Owned(prompt_template.replace(BRANCH_PLACEHOLDER, branch))
+        }
+        _ => Cow::Borrowed(prompt_template),
+    });
+    if let Some(language) = &context.language {
+        sections.push(Cow::Owned(language_instruction(language)));
+    }
+    if let Some(template) = &context.message_template {
+        sections.push(Cow::Owned(template_instruction(template)));
+    }
+    if !context.allowed_types.is_empty() {
+        sections.push(Cow::Owned(allowed_types_instruction(
+            &context.allowed_types,
+        )));
+    }
+    if let Some(message) = &context.previous_message {
+        sections.push(Cow::Owned(previous_message_section(message)));
+    }
+    if let Some(stat) = &context.stat {
+        sections.push(Cow::Borrowed(stat));
+    }
+    sections
+}
+
+/// List recent commit subjects for Claude to match their style
+fn style_context(subjects: &[String]) -> String {
+    let mut section = String::from("Match the style of these recent commits:");
+    for subject in subjects {
+        section.push_str("\n- ");
+        section.push_str(subject);
+    }
+    section
+}
+
+/// Show an earlier message for the same change as a starting point
+fn previous_message_section(message: &str) -> String {
+    format!("Previous message (improve on this):\n{}", message)
+}
+
+/// Instruction telling Claude which language to write the message in
+fn language_instruction(language: &str) -> String {
+    format!("Write the commit message in {}.", language)
+}
+
+/// Instruction telling Claude to fill in the message template
+fn template_instruction(template: &str) -> String {
+    format!(
+        "Write the commit message in exactly this format, replacing each {{placeholder}} \
+         (leave out sections that would be empty):\n{}",
+        template
+    )
+}
+
+/// Instruction restricting the commit types Claude may use
+fn allowed_types_instruction(types: &[String]) -> String {
+    format!("Use only these commit types: {}.", types.join(", "))
+}
+
+/// Calculate the size in bytes of the prompt that would be built
+///
+/// Includes every section and the separators between them, without
+/// allocating the combined string.
+pub fn calculate_prompt_size(diff: &str, prompt_template: &str, context: &PromptContext) -> usize {
+    let sections = leading_sections(prompt_template, context);
+    sections.iter().map(|s| s.len()).sum::<usize>()
+        + SEPARATOR.len() * (sections.len() - 1)
+        + context.diff_separator().len()
+        + context.fence_size()
+        + diff.len()
+}
+
+/// Truncate a diff on a line boundary so that it fits in `max_bytes`
+///
+/// When truncation happens, a marker line
+/// `... [diff truncated, N bytes omitted]` is appended; the marker is
+/// included in the `max_bytes` budget. Diffs that already fit are returned
+/// unchanged.
+///
+/// # Returns
+///
+/// * `(String, bool)` - The (possibly truncated) diff and whether it was truncated
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::prompt::truncate_diff;
+///
+/// let (diff, truncated) = truncate_diff("+a\n+b\n", 100);
+/// assert_eq!(diff, "+a\n+b\n");
+/// assert!(!truncated);
+/// ```
+pub fn truncate_diff(diff: &str, max_bytes: usize) -> (String, bool) {
+    if diff.len() <= max_bytes {
+        return (diff.to_string(), false);
+    }
+
+    // Reserve room for the marker using the largest possible omitted count
+    let reserve = truncation_marker(diff.len()).len();
+    let budget = max_bytes.saturating_sub(reserve);
+
+    // Cut after the last newline that fits within the budget
+    let cut = diff.as_bytes()[..budget]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |idx| idx + 1);
+
+    let mut truncated = diff[..cut].to_string();
+    truncated.push_str(&truncation_marker(diff.len() - cut));
+    (truncated, true)
+}
+
+/// Marker line appended to a truncated diff
+fn truncation_marker(omitted: usize) -> String {
+    format!("... [diff truncated, {} bytes omitted]", omitted)
+}
+
+/// Estimate the number of model tokens in `text`
+///
+/// A simple heuristic, not a real tokenizer:
+/// - ASCII text averages about 4 characters per token
+/// - Each non-ASCII character (CJK, accented letters) counts as one token
+/// - Characters outside the Basic Multilingual Plane (mostly emoji) count as two
+///
+/// This keeps emoji- and CJK-heavy diffs from being wildly under-counted
+/// compared to a plain `chars / 4` estimate.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::prompt::estimate_tokens;
+///
+/// assert_eq!(estimate_tokens("abcdefgh"), 2);
+/// assert_eq!(estimate_tokens("日本語"), 3);
+/// ```
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut ascii_chars: usize = 0;
+    let mut other_tokens = 0;
+
+    for c in text.chars() {
+        match c.len_utf8() {
+            1 => ascii_chars += 1,
+            4 => other_tokens += 2,
+            _ => other_tokens += 1,
+        }
+    }
+
+    ascii_chars.div_ceil(4) + other_tokens
+}
+
+/// Validate that the estimated token count of a prompt is within `max_tokens`
+///
+/// # Errors
+///
+/// * Estimated token count exceeds `max_tokens`
+pub fn validate_token_count(prompt: &str, max_tokens: usize) -> Result<()> {
+    let estimated = estimate_tokens(prompt);
+    if estimated > max_tokens {
+        return Err(Error::TooManyTokens {
+            estimated,
+            max: max_tokens,
+        });
+    }
+    Ok(())
+}
+
+/// Warning for a prompt of `size` bytes above `threshold` of `max_size`
+///
+/// Returns `None` when the prompt is within the threshold or already over
+/// the limit (which is reported as an error instead).
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::prompt::prompt_size_warning;
+///
+/// assert_eq!(
+///     prompt_size_warning(850, 1000, 0.8).as_deref(),
+///     Some("prompt uses 85% of max_prompt_size (850 / 1000 bytes); consider splitting the commit")
+/// );
+/// assert_eq!(prompt_size_warning(500, 1000, 0.8), None);
+/// ```
+pub fn prompt_size_warning(size: usize, max_size: usize, threshold: f32) -> Option<String> {
+    if max_size == 0 || size > max_size || (size as f64) <= f64::from(threshold) * max_size as f64 {
+        return None;
+    }
+    Some(format!(
+        "prompt uses {}% of max_prompt_size ({} / {} bytes); consider splitting the commit",
+        size * 100 / max_size,
+        size,
+        max_size
+    ))
+}
+
 /// Build a prompt by combining the prompt template and git diff
 ///
 /// The final prompt structure is:
 /// ```text
+/// Match the style of these recent commits:   (if present)
+/// - {subject}
+///
 /// {prompt_template}
 ///
 /// {git_diff}
@@ -42,19 +301,75 @@ pub const DEFAULT_MAX_PROMPT_SIZE: usize = 1_000_000;
 /// assert_eq!(prompt, "Generate a commit message:\n\n+added line");
 /// ```
 pub fn build_prompt(diff: &str, prompt_template: &str, max_size: usize) -> Result<String> {
+    build_prompt_with_context(diff, prompt_template, &PromptContext::default(), max_size)
+}
+
+/// Build a prompt including the optional sections from a [`PromptContext`]
+///
+/// Sections are joined with a blank line in this order:
+/// ```text
+/// Match the style of these recent commits:   (if present)
+/// - {subject}
+///
+/// {prompt_template}
+///
+/// Write the commit message in {language}.   (if present)
+///
+/// Write the commit message in exactly this format, ...:   (if present)
+/// {message_template}
+///
+/// Use only these commit types: {allowed_types}.   (if present)
+///
+/// Previous message (improve on this):   (if present)
+/// {previous_message}
+///
+/// {stat}        (if present)
+///
+/// {git_diff}
+/// ```
+///
+/// `context.diff_separator` replaces the blank line right before the diff,
+/// and `context.fence_diff` wraps the diff in a ```` ```diff ```` block.
+/// With `context.prompt_position = After` the diff comes first, followed
+/// by the separator and the other sections in the same order.
+/// `{branch}` in the template is replaced with `context.branch` when set.
+/// The size limit applies to the whole prompt, including every section and separator.
+///
+/// # Errors
+///
+/// * Combined prompt size exceeds `max_size`
+pub fn build_prompt_with_context(
+    diff: &str,
+    prompt_template: &str,
+    context: &PromptContext,
+    max_size: usize,
+) -> Result<String> {
     // Validate size BEFORE allocating the combined string
-    let combined_size = prompt_template.len() + 2 + diff.len(); // 2 = "\n\n"
+    let combined_size = calculate_prompt_size(diff, prompt_template, context);
 
     if combined_size > max_size {
-        anyhow::bail!(
-            "Prompt size ({} bytes) exceeds maximum allowed size ({} bytes). \
-             Consider reducing the size of staged changes or splitting into multiple commits.",
-            combined_size,
-            max_size
-        );
+        return Err(Error::PromptTooLarge {
+            actual: combined_size,
+            max: max_size,
+        });
     }
 
-    Ok(format!("{}\n\n{}", prompt_template, diff))
+    let sections = leading_sections(prompt_template, context).join(SEPARATOR);
+    let diff = if context.fence_diff {
+        Cow::Owned(format!("{}{}{}", DIFF_FENCE_OPEN, diff, DIFF_FENCE_CLOSE))
+    } else {
+        Cow::Borrowed(diff)
+    };
+    let (first, last) = match context.prompt_position {
+        PromptPosition::Before => (sections.as_str(), diff.as_ref()),
+        PromptPosition::After => (diff.as_ref(), sections.as_str()),
+    };
+
+    let mut prompt = String::with_capacity(combined_size);
+    prompt.push_str(first);
+    prompt.push_str(context.diff_separator());
+    prompt.push_str(last);
+    Ok(prompt)
 }
 
 #[cfg(test)]
@@ -256,6 +571,24 @@ mod tests {
         assert!(error_msg.contains("splitting into multiple commits"));
     }
 
+    #[test]
+    fn test_build_prompt_error_is_prompt_too_large() {
+        // Arrange
+        let diff = "+".repeat(100);
+
+        // Act
+        let result = build_prompt(&diff, "Generate:", 50);
+
+        // Assert - callers can read the sizes without parsing the message
+        match result {
+            Err(Error::PromptTooLarge { actual, max }) => {
+                assert_eq!(actual, 111);
+                assert_eq!(max, 50);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
     #[test]
     fn test_build_prompt_custom_size_limit() {
         // Arrange - custom size limit (500 bytes)
@@ -286,4 +619,429 @@ mod tests {
         assert!(error_msg.contains("exceeds maximum allowed size"));
         assert!(error_msg.contains(&custom_limit.to_string()));
     }
+
+    #[test]
+    fn test_build_prompt_with_stat() {
+        // Arrange
+        let context = PromptContext {
+            stat: Some(" src/main.rs | 2 +-\n 1 file changed".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            build_prompt_with_context("+added", "Generate:", &context, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap();
+
+        // Assert - stat block sits between template and diff
+        assert_eq!(
+            result,
+            "Generate:\n\n src/main.rs | 2 +-\n 1 file changed\n\n+added"
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_with_stat_counts_toward_limit() {
+        // Arrange - template + diff fit exactly, stat pushes it over
+        let prompt_template = "Generate:";
+        let diff = "+".repeat(100 - prompt_template.len() - 2);
+        let context = PromptContext {
+            stat: Some("stat".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let without_stat = build_prompt(&diff, prompt_template, 100);
+        let with_stat = build_prompt_with_context(&diff, prompt_template, &context, 100);
+
+        // Assert - stat bytes and its separator are included in the size check
+        assert!(without_stat.is_ok());
+        let error_msg = with_stat.unwrap_err().to_string();
+        assert!(error_msg.contains("106 bytes"));
+    }
+
+    #[test]
+    fn test_calculate_prompt_size_matches_built_prompt() {
+        // Arrange
+        let context = PromptContext {
+            stat: Some("1 file changed".to_string()),
+            language: Some("ja".to_string()),
+            message_template: Some("{type}: {summary}".to_string()),
+            allowed_types: vec!["feat".to_string(), "fix".to_string()],
+            recent_commits: vec!["feat: 日本語".to_string()],
+            diff_separator: None,
+            fence_diff: true,
+            prompt_position: PromptPosition::After,
+            branch: Some("main".to_string()),
+            previous_message: Some("feat: 前回".to_string()),
+        };
+
+        // Act
+        let size = calculate_prompt_size("+日本語", "Generate on {branch}:", &context);
+        let prompt = build_prompt_with_context(
+            "+日本語",
+            "Generate on {branch}:",
+            &context,
+            DEFAULT_MAX_PROMPT_SIZE,
+        )
+        .unwrap();
+
+        // Assert - byte count agrees with the actual prompt
+        assert_eq!(size, prompt.len());
+    }
+
+    #[test]
+    fn test_build_prompt_with_language() {
+        // Arrange
+        let context = PromptContext {
+            language: Some("ja".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            build_prompt_with_context("+added", "Generate:", &context, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap();
+
+        // Assert - instruction sits between template and diff
+        assert_eq!(
+            result,
+            "Generate:\n\nWrite the commit message in ja.\n\n+added"
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_substitutes_branch() {
+        // Arrange
+        let context = PromptContext {
+            branch: Some("feat/login".to_string()),
+            ..Default::default()
+        };
+        let template = "Branch {branch}; use its prefix ({branch}) as the type:";
+
+        // Act
+        let size = calculate_prompt_size("+added", template, &context);
+        let result = build_prompt_with_context("+added", template, &context, 1000).unwrap();
+
+        // Assert - every occurrence replaced, and the size counts the substituted text
+        assert_eq!(
+            result,
+            "Branch feat/login; use its prefix (feat/login) as the type:\n\n+added"
+        );
+        assert_eq!(size, result.len());
+    }
+
+    #[test]
+    fn test_build_prompt_without_branch_keeps_placeholder() {
+        // Arrange / Act
+        let result = build_prompt("+added", "On {branch}:", 1000).unwrap();
+
+        // Assert
+        assert_eq!(result, "On {branch}:\n\n+added");
+    }
+
+    #[test]
+    fn test_build_prompt_prompt_position() {
+        // Arrange
+        let before = PromptContext {
+            language: Some("ja".to_string()),
+            ..Default::default()
+        };
+        let after = PromptContext {
+            prompt_position: PromptPosition::After,
+            ..before.clone()
+        };
+
+        // Act
+        let before = build_prompt_with_context("+added", "Generate:", &before, 100).unwrap();
+        let after = build_prompt_with_context("+added", "Generate:", &after, 100).unwrap();
+
+        // Assert - same bytes, sections moved as a block behind the diff
+        assert_eq!(
+            before,
+            "Generate:\n\nWrite the commit message in ja.\n\n+added"
+        );
+        assert_eq!(
+            after,
+            "+added\n\nGenerate:\n\nWrite the commit message in ja."
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_with_previous_message() {
+        // Arrange
+        let context = PromptContext {
+            previous_message: Some("fix: handle empty diff".to_string()),
+            stat: Some("1 file changed".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result = build_prompt_with_context("+added", "Generate:", &context, 1000).unwrap();
+
+        // Assert - placed right before the stat
+        assert_eq!(
+            result,
+            "Generate:\n\nPrevious message (improve on this):\nfix: handle empty diff\n\n\
+             1 file changed\n\n+added"
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_with_allowed_types() {
+        // Arrange
+        let context = PromptContext {
+            allowed_types: vec!["feat".to_string(), "fix".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let result = build_prompt_with_context("+added", "Generate:", &context, 1000).unwrap();
+
+        // Assert
+        assert_eq!(
+            result,
+            "Generate:\n\nUse only these commit types: feat, fix.\n\n+added"
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_with_message_template() {
+        // Arrange
+        let context = PromptContext {
+            language: Some("ja".to_string()),
+            message_template: Some("{type}: {summary}\n\n{body}".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            build_prompt_with_context("+added", "Generate:", &context, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap();
+
+        // Assert - the template follows the language instruction, verbatim
+        let template_at = result
+            .find("{type}: {summary}\n\n{body}\n\n+added")
+            .unwrap();
+        assert!(result.find("Write the commit message in ja.").unwrap() < template_at);
+        assert!(result.contains("in exactly this format"));
+    }
+
+    #[test]
+    fn test_build_prompt_with_recent_commits() {
+        // Arrange
+        let context = PromptContext {
+            recent_commits: vec!["feat: add login".to_string(), "fix: typo".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            build_prompt_with_context("+added", "Generate:", &context, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap();
+
+        // Assert - style list comes before the template
+        assert_eq!(
+            result,
+            "Match the style of these recent commits:\n- feat: add login\n- fix: typo\n\nGenerate:\n\n+added"
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_with_custom_diff_separator() {
+        // Arrange
+        let context = PromptContext {
+            stat: Some(" a.rs | 1 +".to_string()),
+            diff_separator: Some("\n## Diff\n".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            build_prompt_with_context("+added", "Generate:", &context, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap();
+
+        // Assert - only the separator right before the diff is replaced
+        assert_eq!(result, "Generate:\n\n a.rs | 1 +\n## Diff\n+added");
+    }
+
+    #[test]
+    fn test_build_prompt_with_fenced_diff() {
+        // Arrange
+        let context = PromptContext {
+            fence_diff: true,
+            ..Default::default()
+        };
+
+        // Act
+        let result =
+            build_prompt_with_context("+added", "Generate:", &context, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap();
+
+        // Assert - the diff section starts with the fence
+        assert_eq!(result, "Generate:\n\n```diff\n+added\n```");
+    }
+
+    #[test]
+    fn test_build_prompt_fence_counts_toward_size_limit() {
+        // Arrange - fits unfenced, but not with the 12 bytes of fences
+        let context = PromptContext {
+            fence_diff: true,
+            ..Default::default()
+        };
+        let unfenced_size = "Generate:\n\n+added".len();
+
+        // Act
+        let result = build_prompt_with_context("+added", "Generate:", &context, unfenced_size);
+
+        // Assert
+        assert!(build_prompt("+added", "Generate:", unfenced_size).is_ok());
+        assert!(matches!(result, Err(Error::PromptTooLarge { .. })));
+    }
+
+    #[test]
+    fn test_calculate_prompt_size_uses_diff_separator_length() {
+        // Arrange
+        let empty = PromptContext {
+            diff_separator: Some(String::new()),
+            ..Default::default()
+        };
+        let long = PromptContext {
+            diff_separator: Some("\n\n```diff\n".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let empty_size = calculate_prompt_size("+added", "Generate:", &empty);
+        let long_size = calculate_prompt_size("+added", "Generate:", &long);
+
+        // Assert - size matches the built prompt exactly
+        assert_eq!(empty_size, "Generate:+added".len());
+        assert_eq!(
+            long_size,
+            build_prompt_with_context("+added", "Generate:", &long, DEFAULT_MAX_PROMPT_SIZE)
+                .unwrap()
+                .len()
+        );
+        assert!(build_prompt_with_context("+added", "Generate:", &long, long_size - 1).is_err());
+    }
+
+    #[test]
+    fn test_truncate_diff_within_limit_unchanged() {
+        // Arrange
+        let diff = "+line 1\n+line 2";
+
+        // Act
+        let (result, truncated) = truncate_diff(diff, diff.len());
+
+        // Assert
+        assert_eq!(result, diff);
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test_truncate_diff_cuts_on_line_boundary() {
+        // Arrange - 100 lines of 10 bytes each
+        let diff = "+123456789\n".repeat(100);
+
+        // Act
+        let (result, truncated) = truncate_diff(&diff, 200);
+
+        // Assert - fits the budget, keeps whole lines, reports omitted bytes
+        assert!(truncated);
+        assert!(result.len() <= 200);
+        let (kept, marker) = result.rsplit_once('\n').unwrap();
+        assert!(kept.lines().all(|line| line == "+123456789"));
+        let omitted = diff.len() - kept.len() - 1;
+        assert_eq!(
+            marker,
+            format!("... [diff truncated, {} bytes omitted]", omitted)
+        );
+    }
+
+    #[test]
+    fn test_truncate_diff_multibyte_characters() {
+        // Arrange - multi-byte lines; a byte cut must not split a character
+        let diff = "+日本語の変更\n".repeat(50);
+
+        // Act
+        let (result, truncated) = truncate_diff(&diff, 150);
+
+        // Assert - still valid UTF-8 on whole lines
+        assert!(truncated);
+        assert!(result.len() <= 150);
+        assert!(result.starts_with("+日本語の変更\n"));
+    }
+
+    #[test]
+    fn test_estimate_tokens_ascii() {
+        // Arrange - 40 ASCII characters
+        let text = "fn main() { println!(\"hello world\"); }\n";
+
+        // Act
+        let tokens = estimate_tokens(text);
+
+        // Assert - roughly 4 characters per token
+        assert_eq!(tokens, 10);
+    }
+
+    #[test]
+    fn test_estimate_tokens_cjk_counts_more_than_ascii() {
+        // Arrange - same number of characters, ASCII vs CJK
+        let ascii = "abcdefghij";
+        let cjk = "日本語のコミットメッセ";
+
+        // Act
+        let ascii_tokens = estimate_tokens(ascii);
+        let cjk_tokens = estimate_tokens(cjk);
+
+        // Assert - one token per CJK character, ~4 chars per ASCII token
+        assert_eq!(ascii_tokens, 3);
+        assert_eq!(cjk_tokens, cjk.chars().count());
+        assert!(cjk_tokens > ascii_tokens);
+    }
+
+    #[test]
+    fn test_estimate_tokens_emoji() {
+        // Arrange / Act
+        let tokens = estimate_tokens("🎉🚀");
+
+        // Assert - emoji are not under-counted
+        assert_eq!(tokens, 4);
+    }
+
+    #[test]
+    fn test_validate_token_count() {
+        // Arrange
+        let prompt = "日本語".repeat(10); // ~30 tokens
+
+        // Act / Assert
+        assert!(validate_token_count(&prompt, 30).is_ok());
+        let error_msg = validate_token_count(&prompt, 29).unwrap_err().to_string();
+        assert!(error_msg.contains("~30 tokens"));
+        assert!(error_msg.contains("(29)"));
+    }
+
+    #[test]
+    fn test_prompt_size_warning_fires_above_threshold() {
+        // Arrange / Act
+        let warning = prompt_size_warning(850, 1_000, 0.8);
+
+        // Assert - includes the current percentage
+        assert!(warning.unwrap().contains("85%"));
+    }
+
+    #[test]
+    fn test_prompt_size_warning_silent_below_threshold() {
+        // Arrange / Act / Assert
+        assert_eq!(prompt_size_warning(500, 1_000, 0.8), None);
+        assert_eq!(prompt_size_warning(800, 1_000, 0.8), None);
+    }
+
+    #[test]
+    fn test_prompt_size_warning_silent_over_limit_or_disabled() {
+        // Arrange / Act / Assert - over the limit is an error, not a warning
+        assert_eq!(prompt_size_warning(1_001, 1_000, 0.8), None);
+        assert_eq!(prompt_size_warning(1_000, 1_000, 1.0), None);
+    }
 }
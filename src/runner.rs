@@ -0,0 +1,196 @@
+//! Running external commands behind a replaceable trait
+//!
+//! The git queries that build the prompt (the diff, the diff stat, the
+//! recent commit subjects and the current branch) take the process launcher as a parameter through
+//! their `_with` variants, e.g. [`crate::git::get_git_diff_with`].
+//! [`SystemRunner`] starts real processes, and tests can substitute a fake
+//! that returns canned output without git being installed.
+//!
+//! Other commands start processes directly: `git commit` and the hooks need
+//! the terminal, and the Claude CLI needs a timeout and streaming output,
+//! neither of which a runner that returns the collected [`Output`] supports.
+
+use std::io::{self, Write};
+use std::process::{Command, Output, Stdio};
+
+/// Something that can run a program and collect its output
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::runner::{CommandRunner, SystemRunner};
+/// use std::process::Output;
+///
+/// /// Prints every command before running it
+/// struct TracingRunner;
+///
+/// impl CommandRunner for TracingRunner {
+///     fn run(&self, program: &str, args: &[String], input: Option<&str>) -> std::io::Result<Output> {
+///         eprintln!("$ {} {}", program, args.join(" "));
+///         SystemRunner.run(program, args, input)
+///     }
+/// }
+/// ```
+pub trait CommandRunner: Send + Sync {
+    /// Run `program` with `args`, writing `input` to its stdin when given,
+    /// and wait for it to exit
+    ///
+    /// A non-zero exit status is not an error here; callers inspect
+    /// [`Output::status`] themselves.
+    ///
+    /// # Errors
+    ///
+    /// * The program cannot be started, or its stdin cannot be written
+    fn run(&self, program: &str, args: &[String], input: Option<&str>) -> io::Result<Output>;
+}
+
+/// Runs commands as real child processes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[String], input: Option<&str>) -> io::Result<Output> {
+        let mut command = Command::new(program);
+        command.args(args);
+        let Some(input) = input else {
+            return command.output();
+        };
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Write from another thread so a child that fills its stdout pipe
+        // before reading all of stdin cannot deadlock us
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = input.to_string();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output()?;
+        match writer.join() {
+            Ok(Err(e)) if e.kind() != io::ErrorKind::BrokenPipe => Err(e),
+            _ => Ok(output),
+        }
+    }
+}
+
+/// A [`CommandRunner`] that returns scripted output instead of running anything
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use std::process::ExitStatus;
+    use std::sync::Mutex;
+
+    /// One recorded invocation: program, arguments and stdin
+    pub(crate) type Call = (String, Vec<String>, Option<String>);
+
+    /// Answers each command with the first response whose program and
+    /// leading arguments match, and records every call
+    #[derive(Default)]
+    pub(crate) struct FakeRunner {
+        responses: Vec<(String, Vec<String>, Output)>,
+        pub(crate) calls: Mutex<Vec<Call>>,
+    }
+
+    impl FakeRunner {
+        /// Respond to `program` called with arguments starting with `args_prefix`
+        pub(crate) fn respond(
+            mut self,
+            program: &str,
+            args_prefix: &[&str],
+            code: i32,
+            stdout: &str,
+        ) -> Self {
+            let output = Output {
+                status: exit_status(code),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: Vec::new(),
+            };
+            let prefix = args_prefix.iter().map(|arg| arg.to_string()).collect();
+            self.responses.push((program.to_string(), prefix, output));
+            self
+        }
+
+        /// Programs that were run, in order
+        pub(crate) fn programs(&self) -> Vec<String> {
+            let calls = self.calls.lock().unwrap();
+            calls
+                .iter()
+                .map(|(program, _, _)| program.clone())
+                .collect()
+        }
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, program: &str, args: &[String], input: Option<&str>) -> io::Result<Output> {
+            self.calls.lock().unwrap().push((
+                program.to_string(),
+                args.to_vec(),
+                input.map(str::to_string),
+            ));
+            self.responses
+                .iter()
+                .find(|(p, prefix, _)| p == program && args.starts_with(prefix))
+                .map(|(_, _, output)| output.clone())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no fake response for {} {:?}", program, args),
+                    )
+                })
+        }
+    }
+
+    #[cfg(unix)]
+    fn exit_status(code: i32) -> ExitStatus {
+        std::os::unix::process::ExitStatusExt::from_raw(code << 8)
+    }
+
+    #[cfg(windows)]
+    fn exit_status(code: i32) -> ExitStatus {
+        std::os::windows::process::ExitStatusExt::from_raw(code as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeRunner;
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_writes_input_to_stdin() {
+        // Arrange
+        let input = "line\n".repeat(100_000);
+
+        // Act - large enough to fill the pipe buffers in both directions
+        let output = SystemRunner.run("cat", &[], Some(&input)).unwrap();
+
+        // Assert
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), input.len());
+    }
+
+    #[test]
+    fn test_fake_runner_matches_program_and_args_prefix() {
+        // Arrange
+        let runner = FakeRunner::default()
+            .respond("git", &["diff", "--stat"], 0, "stat")
+            .respond("git", &["diff"], 0, "diff")
+            .respond("git", &["log"], 128, "");
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        // Act
+        let stat = runner.run("git", &args(&["diff", "--stat", "--cached"]), None);
+        let diff = runner.run("git", &args(&["diff", "--cached"]), None);
+        let log = runner.run("git", &args(&["log", "-n", "1"]), None);
+        let missing = runner.run("claude", &args(&["-p"]), Some("prompt"));
+
+        // Assert
+        assert_eq!(stat.unwrap().stdout, b"stat");
+        assert_eq!(diff.unwrap().stdout, b"diff");
+        assert!(!log.unwrap().status.success());
+        assert!(missing.is_err());
+        assert_eq!(runner.programs(), vec!["git", "git", "git", "claude"]);
+    }
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by unit tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory under the system temp dir, removed again on drop
+///
+/// The name includes the process id so concurrent test runs do not collide,
+/// and cleanup also happens when an assertion fails.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create `<temp>/claude_commit_<name>_<pid>`, clearing any leftover from an earlier run
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("claude_commit_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// The directory's path
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// A path inside the directory
+    pub(crate) fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Git trailers appended to generated messages
+//!
+//! Trailers such as `Refs: #123` are added after generation, so they never
+//! count toward the prompt size and are never rewritten by Claude.
+
+/// Normalize a trailer given as `key=value` or `key: value` to `Key: value` form
+///
+/// The key keeps its case; surrounding whitespace is trimmed. Returns `None`
+/// when there is no separator, the key is empty or contains whitespace, or
+/// the value is empty.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::trailer::parse_trailer;
+///
+/// assert_eq!(parse_trailer("Refs=#123").as_deref(), Some("Refs: #123"));
+/// assert_eq!(parse_trailer("Reviewed-by: Alice").as_deref(), Some("Reviewed-by: Alice"));
+/// assert_eq!(parse_trailer("no separator"), None);
+/// ```
+pub fn parse_trailer(trailer: &str) -> Option<String> {
+    let (key, value) = trailer.split_once(['=', ':'])?;
+    let (key, value) = (key.trim(), value.trim());
+    if key.is_empty() || key.contains(char::is_whitespace) || value.is_empty() {
+        return None;
+    }
+    Some(format!("{}: {}", key, value))
+}
+
+/// Append `trailers` to `message`, separated from it by a blank line
+///
+/// Entries are formatted with [`parse_trailer`]; invalid entries are
+/// skipped (they are rejected when the config and arguments are read).
+/// Returns the message unchanged when there is nothing to append.
+///
+/// # Example
+///
+/// ```
+/// use claude_commit::trailer::append_trailers;
+///
+/// let message = append_trailers("fix: handle empty diff", &["Refs=#123".to_string()]);
+/// assert_eq!(message, "fix: handle empty diff\n\nRefs: #123");
+/// ```
+pub fn append_trailers(message: &str, trailers: &[String]) -> String {
+    let lines: Vec<String> = trailers.iter().filter_map(|t| parse_trailer(t)).collect();
+    if lines.is_empty() {
+        return message.to_string();
+    }
+    format!("{}\n\n{}", message.trim_end(), lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trailer_separators() {
+        // Arrange / Act / Assert - `=` and `:` both produce git's `Key: value`
+        assert_eq!(parse_trailer("Refs=#123").as_deref(), Some("Refs: #123"));
+        assert_eq!(parse_trailer("Refs: #123").as_deref(), Some("Refs: #123"));
+        assert_eq!(
+            parse_trailer(" Co-authored-by = A <a@example.com> ").as_deref(),
+            Some("Co-authored-by: A <a@example.com>")
+        );
+    }
+
+    #[test]
+    fn test_parse_trailer_value_may_contain_separators() {
+        // Arrange / Act / Assert - only the first separator splits
+        assert_eq!(
+            parse_trailer("Link=https://example.com/?a=b").as_deref(),
+            Some("Link: https://example.com/?a=b")
+        );
+    }
+
+    #[test]
+    fn test_parse_trailer_invalid() {
+        // Arrange / Act / Assert
+        assert_eq!(parse_trailer("Refs"), None);
+        assert_eq!(parse_trailer("=#123"), None);
+        assert_eq!(parse_trailer("Refs="), None);
+        assert_eq!(parse_trailer("Fixes bug: 1"), None);
+    }
+
+    #[test]
+    fn test_append_trailers_multiple() {
+        // Arrange
+        let message = "feat: add login\n\n- JWT based\n";
+        let trailers = vec!["Refs=#123".to_string(), "Reviewed-by: Bob".to_string()];
+
+        // Act
+        let result = append_trailers(message, &trailers);
+
+        // Assert - one blank line, then one trailer per line in order
+        assert_eq!(
+            result,
+            "feat: add login\n\n- JWT based\n\nRefs: #123\nReviewed-by: Bob"
+        );
+    }
+
+    #[test]
+    fn test_append_trailers_none() {
+        // Arrange / Act / Assert
+        assert_eq!(append_trailers("fix: typo", &[]), "fix: typo");
+    }
+}
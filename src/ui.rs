@@ -1,68 +1,140 @@
 //! User interaction: spinner display and interactive commit flow
 
-use anyhow::Result;
-use std::io::{self, Write};
+use anyhow::{Result, bail};
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::time::{Duration, sleep};
 
-use crate::claude::generate_message;
+use crate::claude::generate_candidates;
+use crate::color;
 use crate::config::Config;
-use crate::git::{run_git_commit, run_git_commit_direct, write_commit_message};
+use crate::git::{CommitOptions, run_git_commit, run_git_commit_direct, write_commit_message};
+use crate::log;
+use crate::prompt::PromptContext;
+
+/// Action chosen by the user for a generated message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Commit directly without opening an editor
+    Accept,
+    /// Open the git commit editor with the message
+    Edit,
+    /// Discard the message and generate a new one
+    Regenerate,
+    /// Cancel the commit
+    Quit,
+}
 
 /// Run the interactive commit flow
 ///
-/// Generates a commit message and prompts the user to:
+/// Generates a commit message (asking the user to pick one when several
+/// candidates are configured) and prompts the user to:
 /// - [A]ccept: commit directly without opening an editor
-/// - [E]dit: open the git commit editor to review/modify before committing
+/// - [E]dit (or `y`): open the git commit editor to review/modify before committing
 /// - [R]egenerate: discard the message and generate a new one
 /// - [Q]uit: cancel the commit
-pub async fn interactive_commit(diff: &str, config: &Config) -> Result<()> {
+///
+/// When stdin is not a terminal, no prompt is shown: the first message is
+/// opened in the git commit editor directly.
+pub async fn interactive_commit(
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+    commit_options: &CommitOptions,
+) -> Result<()> {
+    let interactive = io::stdin().is_terminal();
+    // Regenerating must ask Claude again rather than return the cached message
+    let mut uncached: Option<Config> = None;
+
     loop {
-        let message = generate_with_spinner(diff, config).await?;
+        let generate_config = uncached.as_ref().unwrap_or(config);
+        let mut candidates = generate_with_spinner(diff, generate_config, context).await?;
+        if !interactive {
+            return commit_with_editor(&candidates.swap_remove(0), config, commit_options);
+        }
+        let message = select_candidate(candidates)?;
 
         println!("\nGenerated commit message:");
         println!("─────────────────────────────────────");
         println!("{}", message);
         println!("─────────────────────────────────────");
 
-        loop {
-            print!("\n[A]ccept  [E]dit  [R]egenerate  [Q]uit > ");
-            io::stdout().flush()?;
-
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-
-            match input.trim().to_lowercase().as_str() {
-                "a" | "accept" => {
-                    let msg_file = write_commit_message(&message)?;
-                    run_git_commit_direct(&msg_file)?;
-                    return Ok(());
-                }
-                "e" | "edit" => {
-                    let msg_file = write_commit_message(&message)?;
-                    println!("Launching git commit editor...\n");
-                    run_git_commit(&msg_file)?;
-                    return Ok(());
-                }
-                "r" | "regenerate" => break, // break inner loop → regenerate
-                "q" | "quit" => {
-                    println!("Commit cancelled.");
-                    std::process::exit(0);
-                }
-                _ => {
-                    println!("Invalid input. Please enter A, E, R, or Q.");
-                }
+        match read_action(&mut io::stdin().lock(), &mut io::stdout())? {
+            Action::Accept => {
+                let msg_file = write_commit_message(&message, config.message_file.as_deref())?;
+                run_git_commit_direct(&msg_file, commit_options)?;
+                return Ok(());
+            }
+            Action::Edit => return commit_with_editor(&message, config, commit_options),
+            Action::Regenerate => {
+                uncached.get_or_insert_with(|| Config {
+                    use_cache: false,
+                    ..config.clone()
+                });
+            }
+            Action::Quit => {
+                println!("Commit cancelled.");
+                std::process::exit(0);
             }
         }
     }
 }
 
-/// Generate a commit message with a spinner displayed while waiting
+/// Write the message and open it in the git commit editor
+fn commit_with_editor(
+    message: &str,
+    config: &Config,
+    commit_options: &CommitOptions,
+) -> Result<()> {
+    let msg_file = write_commit_message(message, config.message_file.as_deref())?;
+    log::info("Launching git commit editor...\n");
+    run_git_commit(&msg_file, commit_options)?;
+    Ok(())
+}
+
+/// Prompt for an action until a valid one is entered
+///
+/// # Errors
 ///
-/// Shows a rotating spinner while Claude AI is generating the commit message.
-/// The spinner automatically stops when generation is complete.
-pub async fn generate_with_spinner(diff: &str, config: &Config) -> Result<String> {
+/// * Reading input fails or the input is closed
+pub fn read_action<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> Result<Action> {
+    loop {
+        let line = prompt_line_from(input, output, "\n[A]ccept  [E]dit  [R]egenerate  [Q]uit > ")?;
+        match parse_action(&line) {
+            Some(action) => return Ok(action),
+            None => writeln!(output, "Invalid input. Please enter A, E, R, or Q.")?,
+        }
+    }
+}
+
+/// Parse an action key (case-insensitive, full words accepted)
+fn parse_action(input: &str) -> Option<Action> {
+    match input.trim().to_lowercase().as_str() {
+        "a" | "accept" => Some(Action::Accept),
+        "e" | "edit" | "y" | "yes" => Some(Action::Edit),
+        "r" | "regenerate" => Some(Action::Regenerate),
+        "q" | "quit" => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+/// Generate candidate commit messages with a spinner displayed while waiting
+///
+/// Shows a rotating spinner while Claude AI is generating the commit messages.
+/// The spinner automatically stops when generation is complete. With
+/// `config.stream` the output is shown instead of the spinner. Returns
+/// `config.candidates` messages (at least one).
+pub async fn generate_with_spinner(
+    diff: &str,
+    config: &Config,
+    context: &PromptContext,
+) -> Result<Vec<String>> {
+    // Streamed output shows progress by itself and would be garbled by the spinner
+    if config.stream {
+        return Ok(generate_candidates(diff, config, context, config.candidates).await?);
+    }
+
     let spinner_running = Arc::new(AtomicBool::new(true));
     let spinner_running_clone = Arc::clone(&spinner_running);
 
@@ -81,12 +153,132 @@ pub async fn generate_with_spinner(diff: &str, config: &Config) -> Result<String
         let _ = io::stdout().flush();
     });
 
-    let message = generate_message(diff, config).await?;
+    let messages = generate_candidates(diff, config, context, config.candidates).await?;
 
     spinner_running.store(false, Ordering::Relaxed);
     let _ = spinner_task.await;
 
-    println!("✓ コミットメッセージの生成が完了しました");
+    log::info(color::success("✓ コミットメッセージの生成が完了しました"));
+
+    Ok(messages)
+}
+
+/// Let the user pick one of several candidate messages
+///
+/// Returns the only candidate directly without prompting.
+fn select_candidate(mut candidates: Vec<String>) -> Result<String> {
+    if candidates.len() == 1 {
+        return Ok(candidates.remove(0));
+    }
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        println!("\n[{}] ─────────────────────────────────", i + 1);
+        println!("{}", candidate);
+    }
+    println!("─────────────────────────────────────");
+
+    loop {
+        let input = prompt_line(&format!("\nSelect a message [1-{}] > ", candidates.len()))?;
+
+        match parse_candidate_choice(&input, candidates.len()) {
+            Some(index) => return Ok(candidates.swap_remove(index)),
+            None => println!(
+                "Invalid input. Please enter a number between 1 and {}.",
+                candidates.len()
+            ),
+        }
+    }
+}
+
+/// Print a prompt to stdout and read one line from stdin
+fn prompt_line(prompt: &str) -> Result<String> {
+    prompt_line_from(&mut io::stdin().lock(), &mut io::stdout(), prompt)
+}
+
+/// Print a prompt and read one line of user input
+///
+/// Fails when the input is closed (e.g. the diff was piped in with `--diff-stdin`)
+/// instead of re-prompting forever.
+fn prompt_line_from<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    prompt: &str,
+) -> Result<String> {
+    write!(output, "{}", prompt)?;
+    output.flush()?;
+
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        bail!("stdin is closed; use --json or --no-edit for non-interactive use");
+    }
+    Ok(line)
+}
+
+/// Parse a 1-based candidate number into a 0-based index
+fn parse_candidate_choice(input: &str, count: usize) -> Option<usize> {
+    match input.trim().parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Some(n - 1),
+        _ => None,
+    }
+}
 
-    Ok(message)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_candidate_choice_valid() {
+        // Arrange / Act / Assert - 1-based input, surrounding whitespace allowed
+        assert_eq!(parse_candidate_choice("1\n", 3), Some(0));
+        assert_eq!(parse_candidate_choice(" 3 ", 3), Some(2));
+    }
+
+    #[test]
+    fn test_parse_candidate_choice_invalid() {
+        // Arrange / Act / Assert - out of range or not a number
+        assert_eq!(parse_candidate_choice("0", 3), None);
+        assert_eq!(parse_candidate_choice("4", 3), None);
+        assert_eq!(parse_candidate_choice("a", 3), None);
+        assert_eq!(parse_candidate_choice("", 3), None);
+    }
+
+    #[test]
+    fn test_parse_action() {
+        // Arrange / Act / Assert - keys and words, case-insensitive
+        assert_eq!(parse_action("a\n"), Some(Action::Accept));
+        assert_eq!(parse_action("E"), Some(Action::Edit));
+        assert_eq!(parse_action("y"), Some(Action::Edit));
+        assert_eq!(parse_action(" regenerate "), Some(Action::Regenerate));
+        assert_eq!(parse_action("Q"), Some(Action::Quit));
+        assert_eq!(parse_action("x"), None);
+    }
+
+    #[test]
+    fn test_read_action_reprompts_until_valid() {
+        // Arrange - an invalid key, then regenerate
+        let mut input = io::Cursor::new("x\nr\n");
+        let mut output = Vec::new();
+
+        // Act
+        let action = read_action(&mut input, &mut output).unwrap();
+
+        // Assert
+        assert_eq!(action, Action::Regenerate);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("[Q]uit > ").count(), 2);
+        assert!(output.contains("Invalid input"));
+    }
+
+    #[test]
+    fn test_read_action_fails_on_closed_input() {
+        // Arrange - input ends without a valid choice
+        let mut input = io::Cursor::new("x\n");
+        let mut output = Vec::new();
+
+        // Act
+        let result = read_action(&mut input, &mut output);
+
+        // Assert
+        assert!(result.unwrap_err().to_string().contains("stdin is closed"));
+    }
 }